@@ -8,9 +8,13 @@ use syn::{Expr, ExprArray, ExprLit, Lit, Variant};
 use crate::case::parse_case;
 
 use crate::symbol::{
-    DEFAULT, DENY_UNKNOWN, MAP, NAME, RENAME_ALL, ROOT, SKIP_SERIALIZING, TYPE, TYPE_ATTR,
-    TYPE_CHILD, TYPE_SFC, TYPE_TEXT, TYPE_UNTAG, TYPE_UNTAGGED_ENUM, TYPE_UNTAGGED_STRUCT,
-    VEC_SIZE, WITH_CUSTOM_NS, WITH_NS, XML_SERDE,
+    AT_LEAST, AT_MOST, DEFAULT, DENY_UNKNOWN, DENY_UNKNOWN_ATTRS, DENY_UNKNOWN_CHILDREN,
+    DERIVE_DEFAULT, DESERIALIZE_WITH, ERROR_MODE, FLATTEN_INTO, KEY_ATTR, LIMIT, MAP, NAME, ORDER,
+    PRESERVE_NS, RENAME_ALL, REQUIRED, ROOT, SERIALIZE_WITH, SKIP, SKIP_DESERIALIZING,
+    SKIP_SERIALIZING, SKIP_SERIALIZING_IF, SKIP_SERIALIZING_IF_DEFAULT, TRIM, TYPE, TYPE_ATTR,
+    TYPE_ATTR_MAP, TYPE_CDATA, TYPE_CHILD, TYPE_FLATTEN, TYPE_PI, TYPE_SFC, TYPE_TEXT, TYPE_UNTAG,
+    TYPE_UNTAGGED_ENUM, TYPE_UNTAGGED_STRUCT, VEC_SIZE, WITH_CUSTOM_NS, WITH_NS, XML_SERDE,
+    XML_VALUE,
 };
 
 #[derive(Debug)]
@@ -22,6 +26,7 @@ pub enum ContainerError {
     MissingTypeAttribute(String),
     InvalidTypeValue(String),
     InvalidAttributeName(String, String), // (field_name, invalid_attr_name)
+    Spanned(syn::Error),                  // error pointing at a certain part of the input
 }
 
 impl std::fmt::Display for ContainerError {
@@ -31,9 +36,20 @@ impl std::fmt::Display for ContainerError {
             ContainerError::InvalidVariantAttributes(msg) => write!(f, "Invalid variant attributes: {}", msg),
             ContainerError::InvalidFieldAttributes(msg) => write!(f, "Invalid field attributes: {}", msg),
             ContainerError::InvalidContainerAttributes(msg) => write!(f, "Invalid container attributes: {}", msg),
-            ContainerError::MissingTypeAttribute(field) => write!(f, "Field '{}' is missing the required 'type' attribute. Please specify the type using #[xmlserde(ty = \"...\")]", field),
-            ContainerError::InvalidTypeValue(field) => write!(f, "Field '{}' has an invalid type value. Valid types are: attr, child, text, untag, untagged_enum, untagged_struct", field),
+            ContainerError::MissingTypeAttribute(field) => write!(f, "Field '{}' is missing the required `ty` attribute\nhelp: add #[xmlserde(ty = \"attr\")] or #[xmlserde(ty = \"child\")] to specify the element type", field),
+            ContainerError::InvalidTypeValue(field) => write!(f, "Field '{}' has an invalid type value. Valid types are: attr, child, text, sfc, pi, untag, untagged_enum, untagged_struct", field),
             ContainerError::InvalidAttributeName(field, attr) => write!(f, "Field '{}' has an invalid attribute name '{}'. Did you mean 'name' instead of '{}'?", field, attr, attr),
+            ContainerError::Spanned(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ContainerError {
+    /// Converts into a `syn::Error`, spanning the whole input unless the error has its own span.
+    pub fn into_syn_error(self, input: &syn::DeriveInput) -> syn::Error {
+        match self {
+            | ContainerError::Spanned(e) => e,
+            | e => syn::Error::new_spanned(input, e.to_string()),
         }
     }
 }
@@ -48,8 +64,10 @@ pub struct Container<'a> {
     pub with_ns: Option<syn::LitByteStr>,
     pub custom_ns: Vec<(syn::LitByteStr, syn::LitByteStr)>,
     pub roots: Vec<syn::LitByteStr>,
-    pub deny_unknown: bool,
+    pub deny_unknown_attrs: bool,
+    pub deny_unknown_children: bool,
     pub rename_all: Option<syn::LitStr>,
+    pub derive_default: bool,
 }
 
 impl<'a> Container<'a> {
@@ -82,20 +100,114 @@ impl<'a> Container<'a> {
     }
 
     pub fn validate(&self) -> Result<(), ContainerError> {
-        if !self.roots.is_empty() && self.is_enum() {
+        // The root element of an enum wraps the element of one of its variants, so only the
+        // variants of `child` type with data are allowed.
+        let wrappable = self
+            .enum_variants
+            .iter()
+            .all(|v| matches!(v.ele_type, EleType::Child) && v.ty.is_some());
+        if !self.roots.is_empty() && self.is_enum() && !wrappable {
             return Err(ContainerError::InvalidContainerAttributes(
-                "for clarity, enum should not have the root attribute. please use a struct to wrap the enum and set its type to untag".to_string()
+                "enum with the root attribute should only have `child` variants with data. please use a struct to wrap the enum and set its type to untag".to_string()
             ));
         }
-        if self.deny_unknown && self.is_enum() {
+        if self.derive_default && self.is_enum() {
             return Err(ContainerError::InvalidContainerAttributes(
-                "`deny_unknown_fields` is not supported in enum type".to_string(),
+                "`derive_default` is not supported in enum type".to_string(),
+            ));
+        }
+        if (self.deny_unknown_attrs || self.deny_unknown_children) && self.is_enum() {
+            return Err(ContainerError::InvalidContainerAttributes(
+                "`deny_unknown_fields`, `deny_unknown_attrs` and `deny_unknown_children` are not supported in enum type".to_string(),
             ));
         }
 
+        if let Some(second) = self
+            .enum_variants
+            .iter()
+            .filter(|v| matches!(v.ele_type, EleType::Text))
+            .nth(1)
+        {
+            return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                second.ident,
+                "an enum can only have one `text` variant",
+            )));
+        }
         for field in &self.struct_fields {
             field.validate()?;
         }
+        self.validate_attr_names()?;
+        let text = self
+            .struct_fields
+            .iter()
+            .find(|f| matches!(f.ty, EleType::Text | EleType::CData));
+        let has_children = self.struct_fields.iter().any(|f| {
+            matches!(
+                f.ty,
+                EleType::Child | EleType::SelfClosedChild | EleType::Untag | EleType::UntaggedEnum
+            )
+        });
+        if let (Some(text), true) = (text, has_children) {
+            return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                text.original,
+                "Cannot have the text and children at the same time.",
+            )));
+        }
+        let mut attr_maps = self
+            .struct_fields
+            .iter()
+            .filter(|f| matches!(f.ty, EleType::AttrMap));
+        if let Some(attr_map) = attr_maps.next() {
+            if let Some(second) = attr_maps.next() {
+                return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                    second.original,
+                    "a struct can only have one `attr_map` field",
+                )));
+            }
+            if self.deny_unknown_attrs {
+                return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                    attr_map.original,
+                    "`attr_map` collects the unknown attributes, so it cannot be used with `deny_unknown_fields` or `deny_unknown_attrs`",
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no two `attr` fields resolve to the same attribute name. Attribute names are
+    /// matched case-insensitively when deserializing, so they are compared in the same way.
+    fn validate_attr_names(&self) -> Result<(), ContainerError> {
+        let mut seen = Vec::<(Vec<u8>, String)>::new();
+        for field in self
+            .struct_fields
+            .iter()
+            .filter(|f| matches!(f.ty, EleType::Attr))
+        {
+            let ident = field
+                .original
+                .ident
+                .as_ref()
+                .map(|i| i.to_string())
+                .unwrap_or_default();
+            let mut names = self.get_field_name(field).into_iter().collect::<Vec<_>>();
+            names.extend(field.mapped_names.iter().cloned());
+            for name in names {
+                let name = name.value();
+                let found = seen.iter().find(|(n, _)| n.eq_ignore_ascii_case(&name));
+                if let Some((_, other)) = found {
+                    if *other == ident {
+                        continue;
+                    }
+                    return Err(ContainerError::InvalidFieldAttributes(format!(
+                        "fields `{}` and `{}` have the same attribute name `{}`",
+                        other,
+                        ident,
+                        String::from_utf8_lossy(&name)
+                    )));
+                }
+                seen.push((name, ident.clone()));
+            }
+        }
         Ok(())
     }
 
@@ -168,8 +280,10 @@ impl<'a> Container<'a> {
         let mut with_ns = None;
         let mut custom_ns = Vec::new();
         let mut roots = Vec::new();
-        let mut deny_unknown = false;
+        let mut deny_unknown_attrs = false;
+        let mut deny_unknown_children = false;
         let mut rename_all = None;
+        let mut derive_default = false;
 
         for meta_item in item
             .attrs
@@ -186,7 +300,14 @@ impl<'a> Container<'a> {
             }
             if let Meta::Path(p) = &meta_item {
                 if p == DENY_UNKNOWN {
-                    deny_unknown = true;
+                    deny_unknown_attrs = true;
+                    deny_unknown_children = true;
+                } else if p == DENY_UNKNOWN_ATTRS {
+                    deny_unknown_attrs = true;
+                } else if p == DENY_UNKNOWN_CHILDREN {
+                    deny_unknown_children = true;
+                } else if p == DERIVE_DEFAULT {
+                    derive_default = true;
                 }
             } else if let Some(ns_pair) = Self::parse_custom_ns(&meta_item) {
                 custom_ns.push(ns_pair);
@@ -199,8 +320,10 @@ impl<'a> Container<'a> {
             with_ns,
             custom_ns,
             roots,
-            deny_unknown,
+            deny_unknown_attrs,
+            deny_unknown_children,
             rename_all,
+            derive_default,
         }
     }
 
@@ -224,8 +347,10 @@ impl<'a> Container<'a> {
                     with_ns: attrs.with_ns,
                     custom_ns: attrs.custom_ns,
                     roots: attrs.roots,
-                    deny_unknown: attrs.deny_unknown,
+                    deny_unknown_attrs: attrs.deny_unknown_attrs,
+                    deny_unknown_children: attrs.deny_unknown_children,
                     rename_all: attrs.rename_all,
+                    derive_default: attrs.derive_default,
                 })
             },
             | syn::Data::Enum(de) => {
@@ -241,8 +366,10 @@ impl<'a> Container<'a> {
                     with_ns: attrs.with_ns,
                     custom_ns: attrs.custom_ns,
                     roots: attrs.roots,
-                    deny_unknown: attrs.deny_unknown,
+                    deny_unknown_attrs: attrs.deny_unknown_attrs,
+                    deny_unknown_children: attrs.deny_unknown_children,
                     rename_all: attrs.rename_all,
+                    derive_default: attrs.derive_default,
                 })
             },
             | syn::Data::Union(_) => Err(ContainerError::UnionNotSupported),
@@ -284,6 +411,9 @@ pub struct FieldsSummary<'a> {
     pub text: Option<StructField<'a>>,
     pub attrs: Vec<StructField<'a>>,
     pub self_closed_children: Vec<StructField<'a>>,
+    pub pis: Vec<StructField<'a>>,
+    pub flattened: Vec<StructField<'a>>,
+    pub attr_map: Option<StructField<'a>>,
     pub untagged_enums: Vec<StructField<'a>>,
     pub untagged_structs: Vec<StructField<'a>>,
 }
@@ -296,17 +426,24 @@ impl<'a> FieldsSummary<'a> {
             text: None,
             attrs: vec![],
             self_closed_children: vec![],
+            pis: vec![],
+            flattened: vec![],
+            attr_map: None,
             untagged_enums: vec![],
             untagged_structs: vec![],
         };
         fields.into_iter().for_each(|f| match f.ty {
             | EleType::Attr => result.attrs.push(f),
             | EleType::Child => result.children.push(f),
-            | EleType::Text => result.text = Some(f),
+            | EleType::Text | EleType::CData => result.text = Some(f),
             | EleType::SelfClosedChild => result.self_closed_children.push(f),
+            | EleType::PI => result.pis.push(f),
+            | EleType::Flatten => result.flattened.push(f),
+            | EleType::AttrMap => result.attr_map = Some(f),
             | EleType::Untag => result.untagged_enums.push(f),
             | EleType::UntaggedEnum => result.untagged_enums.push(f),
             | EleType::UntaggedStruct => result.untagged_structs.push(f),
+            | EleType::Skip => {},
         });
         result
     }
@@ -318,10 +455,28 @@ pub struct StructField<'a> {
     pub name: Option<syn::LitByteStr>,
     pub mapped_names: Vec<syn::LitByteStr>,
     pub skip_serializing: bool,
+    pub skip_serializing_if_default: bool,
+    pub skip_serializing_if: Option<syn::ExprPath>,
+    pub skip_deserializing: bool,
     pub default: Option<syn::ExprPath>,
     pub original: &'a syn::Field,
     pub vec_size: Option<syn::Lit>,
+    pub limit: Option<syn::LitInt>,
+    pub flatten_into: Option<syn::LitByteStr>,
+    pub key_attr: Option<syn::LitByteStr>,
+    pub error_mode: ErrorMode,
+    pub at_most: Option<syn::LitInt>,
+    pub at_least: Option<syn::LitInt>,
+    pub serialize_with: Option<syn::ExprPath>,
+    pub deserialize_with: Option<syn::ExprPath>,
+    pub order: Option<syn::LitInt>,
     pub generic: Generic<'a>,
+    pub trim: bool,
+    pub preserve_ns: bool,
+    pub required: bool,
+    /// Set on the copies read by `__deserialize_into`, where the field is read into an `Option`
+    /// which tells whether it appears in the XML.
+    pub patching: bool,
 }
 
 impl<'a> StructField<'a> {
@@ -335,6 +490,158 @@ impl<'a> StructField<'a> {
                 "untagged types doesn't need a name".to_string(),
             ));
         }
+        if let Some(limit) = &self.limit {
+            if limit.base10_parse::<usize>().map_or(true, |l| l == 0) {
+                return Err(ContainerError::InvalidFieldAttributes(
+                    "`limit` should be greater than 0".to_string(),
+                ));
+            }
+            let vec_field = self.generic.is_vec()
+                && matches!(
+                    self.ty,
+                    EleType::Child | EleType::Untag | EleType::UntaggedEnum
+                );
+            if !vec_field {
+                return Err(ContainerError::InvalidFieldAttributes(
+                    "`limit` is only supported on `Vec<T>` fields of `child`, `untag` or `untagged_enum` type".to_string(),
+                ));
+            }
+        }
+        if self.flatten_into.is_some()
+            && !(self.generic.is_vec() && matches!(self.ty, EleType::Child))
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`flatten_into` is only supported on `Vec<T>` fields of `child` type".to_string(),
+            ));
+        }
+        if self.key_attr.is_some()
+            && !(matches!(self.ty, EleType::Child)
+                && self.default.is_none()
+                && self.limit.is_none())
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`key_attr` is only supported in `child` type without `default` or `limit`"
+                    .to_string(),
+            ));
+        }
+        if matches!(self.ty, EleType::PI) && (!self.generic.is_opt() || self.name.is_none()) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`pi` fields should be `Option<T>` with the target as the `name`".to_string(),
+            ));
+        }
+        if matches!(self.ty, EleType::Flatten)
+            && !(matches!(self.generic, Generic::None)
+                && self.name.is_none()
+                && self.mapped_names.is_empty()
+                && self.default.is_none())
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`flatten` fields should be a plain struct without `name`, `map` or `default`"
+                    .to_string(),
+            ));
+        }
+        if matches!(self.ty, EleType::AttrMap)
+            && !(matches!(self.generic, Generic::None)
+                && self.name.is_none()
+                && self.mapped_names.is_empty()
+                && self.default.is_none())
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`attr_map` fields should be a map like `HashMap<String, String>` without `name`, `map` or `default`"
+                    .to_string(),
+            ));
+        }
+        if matches!(self.ty, EleType::Text | EleType::CData) && self.generic.is_vec() {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "text element cannot be Vec<T>; use a String or collect multiple text nodes"
+                    .to_string(),
+            ));
+        }
+        if self.at_most.is_some() || self.at_least.is_some() {
+            if !(matches!(self.generic, Generic::None)
+                && matches!(self.ty, EleType::Child)
+                && self.default.is_none())
+            {
+                return Err(ContainerError::InvalidFieldAttributes(
+                    "`at_most` and `at_least` are only supported on the `child` fields that are neither `Vec<T>` nor `Option<T>` and have no `default`".to_string(),
+                ));
+            }
+            let parse = |l: &Option<syn::LitInt>| l.as_ref().map(|l| l.base10_parse::<usize>());
+            if let (Some(Ok(most)), Some(Ok(least))) = (parse(&self.at_most), parse(&self.at_least))
+            {
+                if least > most {
+                    return Err(ContainerError::InvalidFieldAttributes(
+                        "`at_least` should not be greater than `at_most`".to_string(),
+                    ));
+                }
+            }
+        }
+        if self.order.is_some() && !matches!(self.ty, EleType::Attr | EleType::Child) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`order` is only supported in `attr` and `child` type".to_string(),
+            ));
+        }
+        if (self.serialize_with.is_some() || self.deserialize_with.is_some())
+            && !matches!(self.ty, EleType::Attr | EleType::Text | EleType::CData)
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`serialize_with` and `deserialize_with` are only supported in `attr` and `text` type"
+                    .to_string(),
+            ));
+        }
+        if self.skip_serializing_if.is_some()
+            && !matches!(
+                self.ty,
+                EleType::Attr | EleType::Child | EleType::Text | EleType::CData
+            )
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`skip_serializing_if` is only supported in `attr`, `child` and `text` type"
+                    .to_string(),
+            ));
+        }
+        if !matches!(self.error_mode, ErrorMode::Panic) && !matches!(self.ty, EleType::Attr) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`error_mode` is only supported in `attr` type".to_string(),
+            ));
+        }
+        if self.trim && !matches!(self.ty, EleType::Text | EleType::CData) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`trim` is only supported in `text` type".to_string(),
+            ));
+        }
+        if self.preserve_ns && !matches!(self.ty, EleType::Child) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`preserve_ns` is only supported in `child` type".to_string(),
+            ));
+        }
+        if self.required
+            && !(self.generic.is_opt()
+                && matches!(
+                    self.ty,
+                    EleType::Attr | EleType::Child | EleType::Text | EleType::CData
+                ))
+        {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`required` is only supported on `Option<T>` fields of `attr`, `child` or `text` type"
+                    .to_string(),
+            ));
+        }
+        if self.skip_deserializing && self.required {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`required` cannot be used with `skip_deserializing`".to_string(),
+            ));
+        }
+        if matches!(self.generic, Generic::Res(..)) && !matches!(self.ty, EleType::Child) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`Result<T, E>` fields are only supported in `child` type".to_string(),
+            ));
+        }
+        if matches!(self.generic, Generic::BoxedOpt(_)) && !matches!(self.ty, EleType::Child) {
+            return Err(ContainerError::InvalidFieldAttributes(
+                "`Box<Option<T>>` fields are only supported in `child` type".to_string(),
+            ));
+        }
         Ok(())
     }
 
@@ -344,9 +651,13 @@ impl<'a> StructField<'a> {
                 if let Ok(s) = get_lit_str(&m.value) {
                     return match s.value().as_str() {
                         | s if s == TYPE_ATTR.value() => Ok(EleType::Attr),
+                        | s if s == TYPE_ATTR_MAP.value() => Ok(EleType::AttrMap),
                         | s if s == TYPE_CHILD.value() => Ok(EleType::Child),
                         | s if s == TYPE_TEXT.value() => Ok(EleType::Text),
+                        | s if s == TYPE_CDATA.value() => Ok(EleType::CData),
                         | s if s == TYPE_SFC.value() => Ok(EleType::SelfClosedChild),
+                        | s if s == TYPE_PI.value() => Ok(EleType::PI),
+                        | s if s == TYPE_FLATTEN.value() => Ok(EleType::Flatten),
                         | s if s == TYPE_UNTAG.value() => Ok(EleType::Untag),
                         | s if s == TYPE_UNTAGGED_ENUM.value() => Ok(EleType::UntaggedEnum),
                         | s if s == TYPE_UNTAGGED_STRUCT.value() => Ok(EleType::UntaggedStruct),
@@ -391,9 +702,26 @@ impl<'a> StructField<'a> {
         let mut name = None;
         let mut mapped_names = Vec::new();
         let mut skip_serializing = false;
+        let mut skip_serializing_if_default = false;
+        let mut skip_serializing_if = None;
+        let mut skip_deserializing = false;
+        let mut skip = false;
         let mut default = None;
         let mut ty = None;
         let mut vec_size = None;
+        let mut limit = None;
+        let mut flatten_into = None;
+        let mut key_attr = None;
+        let mut error_mode = ErrorMode::Panic;
+        let mut at_most = None;
+        let mut at_least = None;
+        let mut serialize_with = None;
+        let mut deserialize_with = None;
+        let mut order = None;
+        let mut trim = false;
+        let mut preserve_ns = false;
+        let mut required = false;
+        let mut display_fromstr = false;
 
         for meta_item in f.attrs.iter().flat_map(get_xmlserde_meta_items).flatten() {
             match &meta_item {
@@ -435,6 +763,107 @@ impl<'a> StructField<'a> {
                         if let Some(d) = Self::parse_default(&meta_item) {
                             default = Some(d);
                         }
+                    } else if m.path == LIMIT {
+                        match &m.value {
+                            | syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Int(i),
+                                ..
+                            }) => limit = Some(i.clone()),
+                            | _ => {
+                                return Err(ContainerError::InvalidFieldAttributes(
+                                    "limit must be an integer literal".to_string(),
+                                ))
+                            },
+                        }
+                    } else if m.path == FLATTEN_INTO {
+                        let s = get_lit_byte_str(&m.value).map_err(|_| {
+                            ContainerError::InvalidFieldAttributes(
+                                "flatten_into must be a byte string literal".to_string(),
+                            )
+                        })?;
+                        flatten_into = Some(s.clone());
+                    } else if m.path == KEY_ATTR {
+                        let s = get_lit_byte_str(&m.value).map_err(|_| {
+                            ContainerError::InvalidFieldAttributes(
+                                "key_attr must be a byte string literal".to_string(),
+                            )
+                        })?;
+                        key_attr = Some(s.clone());
+                    } else if m.path == AT_MOST || m.path == AT_LEAST {
+                        let i = match &m.value {
+                            | syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Int(i),
+                                ..
+                            }) => i.clone(),
+                            | _ => {
+                                return Err(ContainerError::InvalidFieldAttributes(
+                                    "at_most and at_least must be integer literals".to_string(),
+                                ))
+                            },
+                        };
+                        if m.path == AT_MOST {
+                            at_most = Some(i);
+                        } else {
+                            at_least = Some(i);
+                        }
+                    } else if m.path == ORDER {
+                        match &m.value {
+                            | syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Int(i),
+                                ..
+                            }) if i.base10_parse::<i64>().is_ok() => order = Some(i.clone()),
+                            | _ => {
+                                return Err(ContainerError::InvalidFieldAttributes(
+                                    "order must be an integer literal".to_string(),
+                                ))
+                            },
+                        }
+                    } else if m.path == SERIALIZE_WITH || m.path == DESERIALIZE_WITH {
+                        let path = parse_lit_into_expr_path(&m.value).map_err(|_| {
+                            ContainerError::InvalidFieldAttributes(
+                                "serialize_with and deserialize_with must be function paths in string literals".to_string(),
+                            )
+                        })?;
+                        if m.path == SERIALIZE_WITH {
+                            serialize_with = Some(path);
+                        } else {
+                            deserialize_with = Some(path);
+                        }
+                    } else if m.path == SKIP_SERIALIZING_IF {
+                        let path = parse_lit_into_expr_path(&m.value).map_err(|_| {
+                            ContainerError::InvalidFieldAttributes(
+                                "skip_serializing_if must be a function path in a string literal"
+                                    .to_string(),
+                            )
+                        })?;
+                        skip_serializing_if = Some(path);
+                    } else if m.path == XML_VALUE {
+                        // Goes through `XmlValueFromStr` instead of an `XmlValue` impl
+                        match get_lit_str(&m.value).map(|s| s.value()) {
+                            | Ok(s) if s == "display_fromstr" => display_fromstr = true,
+                            | _ => {
+                                return Err(ContainerError::InvalidFieldAttributes(
+                                    r#"xml_value must be "display_fromstr""#.to_string(),
+                                ))
+                            },
+                        }
+                    } else if m.path == ERROR_MODE {
+                        error_mode =
+                            match get_lit_str(&m.value).map(|s| s.value()) {
+                                | Ok(s) if s == "panic" => ErrorMode::Panic,
+                                | Ok(s) if s == "ignore" => ErrorMode::Ignore,
+                                | Ok(s) if s == "default" => ErrorMode::Default,
+                                | _ => return Err(ContainerError::InvalidFieldAttributes(
+                                    r#"error_mode must be one of "panic", "ignore" and "default""#
+                                        .to_string(),
+                                )),
+                            };
+                    } else if m.path == TRIM {
+                        trim = get_lit_bool(&m.value).map_err(|_| {
+                            ContainerError::InvalidFieldAttributes(
+                                "trim must be a boolean literal".to_string(),
+                            )
+                        })?;
                     } else {
                         // Check for common typos
                         let attr_name = m.path.get_ident().map(|i| i.to_string());
@@ -448,54 +877,222 @@ impl<'a> StructField<'a> {
                 | Meta::Path(p) if *p == SKIP_SERIALIZING => {
                     skip_serializing = true;
                 },
+                | Meta::Path(p) if *p == SKIP_SERIALIZING_IF_DEFAULT => {
+                    skip_serializing_if_default = true;
+                },
+                | Meta::Path(p) if *p == SKIP_DESERIALIZING => {
+                    skip_deserializing = true;
+                },
+                | Meta::Path(p) if *p == SKIP => {
+                    skip = true;
+                },
+                | Meta::Path(p) if *p == PRESERVE_NS => {
+                    preserve_ns = true;
+                },
+                | Meta::Path(p) if *p == REQUIRED => {
+                    required = true;
+                },
                 | _ => {},
             }
         }
 
-        // Defensive: If ty is missing, return a clear error
-        let ty = ty.ok_or_else(|| ContainerError::MissingTypeAttribute(field_name.clone()))?;
+        if display_fromstr {
+            if serialize_with.is_some() || deserialize_with.is_some() {
+                return Err(ContainerError::InvalidFieldAttributes(
+                    "`xml_value` cannot be used with `serialize_with` or `deserialize_with`"
+                        .to_string(),
+                ));
+            }
+            serialize_with = Some(syn::parse_quote!(::xmlserde::__display_serialize));
+            deserialize_with = Some(syn::parse_quote!(::xmlserde::__from_str_deserialize));
+        }
+        if skip {
+            if ty.is_some() || name.is_some() {
+                return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                    f,
+                    "`skip` cannot be used with `ty` or `name`",
+                )));
+            }
+            ty = Some(EleType::Skip);
+            skip_serializing = true;
+            skip_deserializing = true;
+        }
+        // Point at the field missing `ty`, which is the most common mistake
+        let ty = ty.ok_or_else(|| {
+            let e = ContainerError::MissingTypeAttribute(field_name.clone());
+            ContainerError::Spanned(syn::Error::new_spanned(f, e.to_string()))
+        })?;
         Ok(FieldAttrs {
             name,
             mapped_names,
             skip_serializing,
+            skip_serializing_if_default,
+            skip_serializing_if,
+            skip_deserializing,
             default,
             ty,
             vec_size,
+            limit,
+            flatten_into,
+            key_attr,
+            error_mode,
+            at_most,
+            at_least,
+            serialize_with,
+            deserialize_with,
+            order,
+            trim,
+            preserve_ns,
+            required,
         })
     }
 
     pub fn from_ast(f: &'a syn::Field) -> Result<Self, ContainerError> {
         let attrs = Self::parse_field_attrs(f)?;
-        let generic = get_generics(&f.ty);
+        // A map keyed by an attribute is read like a `Vec<V>` of its values
+        let generic =
+            match (&attrs.key_attr, get_map_types(&f.ty)) {
+                | (Some(_), Some((_, v))) => Generic::Vec(v),
+                | (Some(_), None) => return Err(ContainerError::Spanned(syn::Error::new_spanned(
+                    &f.ty,
+                    "`key_attr` is only supported on `HashMap<K, V>` and `BTreeMap<K, V>` fields",
+                ))),
+                | (None, _) => get_generics(&f.ty),
+            };
 
         // Remove fallback name assignment: do not assign a name if neither name nor mapped_names are present.
         // Let get_field_name handle rename_all case conversion at runtime.
         let name = attrs.name;
         let mapped_names = attrs.mapped_names;
 
+        // `skip_serializing_if_default` implies `default = "T::default"`
+        let default = match attrs.default {
+            | None if attrs.skip_serializing_if_default => {
+                let ty = &f.ty;
+                Some(syn::parse_quote!(<#ty as ::std::default::Default>::default))
+            },
+            | d => d,
+        };
+
         Ok(StructField {
             ty: attrs.ty,
             name,
             mapped_names,
             skip_serializing: attrs.skip_serializing,
-            default: attrs.default,
+            skip_serializing_if_default: attrs.skip_serializing_if_default,
+            skip_serializing_if: attrs.skip_serializing_if,
+            skip_deserializing: attrs.skip_deserializing,
+            default,
             original: f,
             vec_size: attrs.vec_size,
+            limit: attrs.limit,
+            flatten_into: attrs.flatten_into,
+            key_attr: attrs.key_attr,
+            error_mode: attrs.error_mode,
+            at_most: attrs.at_most,
+            at_least: attrs.at_least,
+            serialize_with: attrs.serialize_with,
+            deserialize_with: attrs.deserialize_with,
+            order: attrs.order,
             generic,
+            trim: attrs.trim,
+            preserve_ns: attrs.preserve_ns,
+            required: attrs.required,
+            patching: false,
         })
     }
 
+    /// Whether the field is a `Vec<T>`-like collection other than `Vec<T>` itself, which is
+    /// collected from the `Vec<T>` used while deserializing.
+    pub fn is_collected(&self) -> bool {
+        if self.default.is_some() || !self.generic.is_vec() {
+            return false;
+        }
+        match &self.original.ty {
+            | syn::Type::Path(p) => p.path.segments.last().map_or(false, |s| s.ident != "Vec"),
+            | syn::Type::Array(_) => true,
+            | _ => false,
+        }
+    }
+
+    /// The `T` of a `Saturating<T>` or `Option<Saturating<T>>` field.
+    pub fn saturating_inner(&self) -> Option<&'a syn::Type> {
+        let ty = match self.generic {
+            | Generic::Opt(t) => t,
+            | Generic::None => &self.original.ty,
+            | _ => return None,
+        };
+        match ty {
+            | syn::Type::Path(p) => get_generic_type(&p.path, "Saturating"),
+            | _ => None,
+        }
+    }
+
+    /// The key for sorting the fields in serializing. The fields without `order` come last.
+    pub fn order_key(&self) -> i64 {
+        self.order
+            .as_ref()
+            .and_then(|o| o.base10_parse().ok())
+            .unwrap_or(i64::MAX)
+    }
+
+    /// Converts the `Vec<T>` used while deserializing into the type of the field.
+    pub fn collect_from_vec(&self, v: TokenStream) -> TokenStream {
+        match &self.original.ty {
+            | syn::Type::Array(_) => {
+                let name = match &self.name {
+                    | Some(n) => String::from_utf8_lossy(&n.value()).into_owned(),
+                    | None => self
+                        .original
+                        .ident
+                        .as_ref()
+                        .map(|i| i.to_string())
+                        .unwrap_or_default(),
+                };
+                quote::quote! {::xmlserde::__into_array(#v, #name)?}
+            },
+            | _ => quote::quote! {#v.into_iter().collect()},
+        }
+    }
+
+    /// The type of the `Vec` collecting the field while deserializing. The entries of a map with
+    /// `key_attr` are collected along with their keys.
+    pub fn vec_elem_type(&self) -> Option<TokenStream> {
+        let v = self.generic.get_vec()?;
+        match (&self.key_attr, get_map_types(&self.original.ty)) {
+            | (Some(_), Some((k, _))) => Some(quote::quote! {(#k, #v)}),
+            | _ => Some(quote::quote! {#v}),
+        }
+    }
+
+    /// The copy of the field read by `__deserialize_into`. A patch does not need to repeat the
+    /// `required` fields.
+    pub fn patched(&self) -> Self {
+        StructField {
+            required: false,
+            patching: true,
+            ..self.clone()
+        }
+    }
+
     pub fn is_required(&self) -> bool {
         if matches!(self.ty, EleType::Untag) || matches!(self.ty, EleType::UntaggedEnum) {
             return match self.generic {
                 | Generic::Vec(_) => false,
                 | Generic::Opt(_) => false,
                 | Generic::Boxed(_) => false,
+                | Generic::BoxedOpt(_) | Generic::Res(..) => false,
                 | Generic::None => true,
             };
         }
-        self.default.is_none()
-            && matches!(self.generic, Generic::None)
+        if matches!(self.ty, EleType::AttrMap) {
+            return false;
+        }
+        // A boxed attribute is an `XmlValue` by itself, so it is handled like a plain one.
+        let plain = matches!(self.generic, Generic::None)
+            || (matches!(self.generic, Generic::Boxed(_)) && matches!(self.ty, EleType::Attr));
+        (self.default.is_none() || self.patching)
+            && plain
             && !matches!(self.ty, EleType::SelfClosedChild)
     }
 }
@@ -601,8 +1198,13 @@ impl<'a> EnumVariant<'a> {
 #[derive(Clone)]
 pub enum EleType {
     Attr,
+    /// A map like `HashMap<String, String>` collecting the attributes no other field takes.
+    AttrMap,
     Child,
     Text,
+    /// A text written as CDATA sections, like `<![CDATA[a < b]]>`. Both CDATA sections and
+    /// escaped text are read.
+    CData,
     ///
     /// ```
     /// struct Font {
@@ -617,10 +1219,27 @@ pub enum EleType {
     /// </font>
     /// In this case, </b> indicates the field *bold* is true and <i/> indicates *italic* is true.
     SelfClosedChild,
+    /// A processing instruction inside the element whose target is the `name`, like
+    /// `<?xml-stylesheet href="a.css"?>`. The field is an `Option<T>` holding its data.
+    PI,
+    /// A struct whose attributes are inlined into the element, without a wrapper element.
+    Flatten,
     /// Deprecated, use `UntaggedEnum`
     Untag,
     UntaggedEnum,
     UntaggedStruct,
+    /// A field set by `skip`, which does not appear in the XML at all.
+    Skip,
+}
+
+/// What to do when an attribute value fails to deserialize.
+#[derive(Clone, Copy)]
+pub enum ErrorMode {
+    Panic,
+    /// Keep the field as if the attribute were missing.
+    Ignore,
+    /// Use the `default` function of the field, or `Default::default()`.
+    Default,
 }
 
 pub enum Derive {
@@ -648,6 +1267,15 @@ fn get_lit_byte_str(expr: &syn::Expr) -> Result<&syn::LitByteStr, ()> {
     Err(())
 }
 
+fn get_lit_bool(lit: &syn::Expr) -> Result<bool, ()> {
+    if let syn::Expr::Lit(lit) = lit {
+        if let syn::Lit::Bool(b) = &lit.lit {
+            return Ok(b.value);
+        }
+    }
+    Err(())
+}
+
 fn get_lit_str(lit: &syn::Expr) -> Result<&syn::LitStr, ()> {
     if let syn::Expr::Lit(lit) = lit {
         if let syn::Lit::Str(l) = &lit.lit {
@@ -703,6 +1331,26 @@ fn get_generic_type_from_args(
     }
 }
 
+fn get_result_types(path: &syn::Path) -> Option<(&syn::Type, &syn::Type)> {
+    let seg = path.segments.last()?;
+    if seg.ident != "Result" {
+        return None;
+    }
+    let args = match &seg.arguments {
+        | syn::PathArguments::AngleBracketed(a) => &a.args,
+        | _ => return None,
+    };
+    if args.len() != 2 {
+        return None;
+    }
+    match (args.first(), args.last()) {
+        | (Some(syn::GenericArgument::Type(t)), Some(syn::GenericArgument::Type(e))) => {
+            Some((t, e))
+        },
+        | _ => None,
+    }
+}
+
 fn get_generic_type<'a>(path: &'a syn::Path, type_name: &str) -> Option<&'a syn::Type> {
     let seg = path.segments.last()?;
     if seg.ident != type_name {
@@ -714,21 +1362,56 @@ fn get_generic_type<'a>(path: &'a syn::Path, type_name: &str) -> Option<&'a syn:
     }
 }
 
-pub(crate) fn get_generics(t: &syn::Type) -> Generic {
+/// The key and value types of a `HashMap<K, V>` or `BTreeMap<K, V>`.
+pub(crate) fn get_map_types(t: &syn::Type) -> Option<(&syn::Type, &syn::Type)> {
+    let seg = match t {
+        | syn::Type::Path(p) => p.path.segments.last()?,
+        | _ => return None,
+    };
+    if seg.ident != "HashMap" && seg.ident != "BTreeMap" {
+        return None;
+    }
+    let args = match &seg.arguments {
+        | syn::PathArguments::AngleBracketed(a) => &a.args,
+        | _ => return None,
+    };
+    match (args.len(), args.first(), args.last()) {
+        | (2, Some(syn::GenericArgument::Type(k)), Some(syn::GenericArgument::Type(v))) => {
+            Some((k, v))
+        },
+        | _ => None,
+    }
+}
+
+pub(crate) fn get_generics(t: &syn::Type) -> Generic<'_> {
     let path = match t {
         | syn::Type::Path(p) => &p.path,
+        // A `[T; N]` is collected into a `Vec<T>` and converted at the end.
+        | syn::Type::Array(a) => return Generic::Vec(&a.elem),
         | _ => return Generic::None,
     };
 
     if let Some(ty) = get_generic_type(path, "Vec") {
         return Generic::Vec(ty);
     }
+    // A `LinkedList<T>` is collected into a `Vec<T>` and converted at the end.
+    if let Some(ty) = get_generic_type(path, "LinkedList") {
+        return Generic::Vec(ty);
+    }
     if let Some(ty) = get_generic_type(path, "Option") {
         return Generic::Opt(ty);
     }
     if let Some(ty) = get_generic_type(path, "Box") {
+        if let syn::Type::Path(p) = ty {
+            if let Some(inner) = get_generic_type(&p.path, "Option") {
+                return Generic::BoxedOpt(inner);
+            }
+        }
         return Generic::Boxed(ty);
     }
+    if let Some((ty, err)) = get_result_types(path) {
+        return Generic::Res(ty, err);
+    }
     Generic::None
 }
 
@@ -737,6 +1420,9 @@ pub enum Generic<'a> {
     Vec(&'a syn::Type),
     Opt(&'a syn::Type),
     Boxed(&'a syn::Type),
+    /// `Box<Option<T>>`
+    BoxedOpt(&'a syn::Type),
+    Res(&'a syn::Type, &'a syn::Type),
     None,
 }
 
@@ -789,8 +1475,10 @@ pub struct ContainerAttrs {
     pub with_ns: Option<syn::LitByteStr>,
     pub custom_ns: Vec<(syn::LitByteStr, syn::LitByteStr)>,
     pub roots: Vec<syn::LitByteStr>,
-    pub deny_unknown: bool,
+    pub deny_unknown_attrs: bool,
+    pub deny_unknown_children: bool,
     pub rename_all: Option<syn::LitStr>,
+    pub derive_default: bool,
 }
 
 // Define struct for field attributes
@@ -798,7 +1486,22 @@ pub struct FieldAttrs {
     pub name: Option<syn::LitByteStr>,
     pub mapped_names: Vec<syn::LitByteStr>,
     pub skip_serializing: bool,
+    pub skip_serializing_if_default: bool,
+    pub skip_serializing_if: Option<syn::ExprPath>,
+    pub skip_deserializing: bool,
     pub default: Option<syn::ExprPath>,
     pub ty: EleType,
     pub vec_size: Option<syn::Lit>,
+    pub limit: Option<syn::LitInt>,
+    pub flatten_into: Option<syn::LitByteStr>,
+    pub key_attr: Option<syn::LitByteStr>,
+    pub error_mode: ErrorMode,
+    pub at_most: Option<syn::LitInt>,
+    pub at_least: Option<syn::LitInt>,
+    pub serialize_with: Option<syn::ExprPath>,
+    pub deserialize_with: Option<syn::ExprPath>,
+    pub order: Option<syn::LitInt>,
+    pub trim: bool,
+    pub preserve_ns: bool,
+    pub required: bool,
 }
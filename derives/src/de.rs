@@ -1,16 +1,17 @@
-use quote::{format_ident, quote};
+use quote::{format_ident, quote, quote_spanned};
+use syn::spanned::Spanned;
 use syn::DeriveInput;
 
 use crate::{
     case::parse_case,
-    container::{self, Container, EleType, FieldsSummary, Generic, StructField},
+    container::{self, Container, EleType, ErrorMode, FieldsSummary, Generic, StructField},
 };
 
 pub fn get_de_impl_block(input: DeriveInput) -> proc_macro2::TokenStream {
     let container = Container::from_ast(&input, container::Derive::Deserialize)
         .expect("Failed to parse container");
     if let Err(e) = container.validate() {
-        return syn::Error::new_spanned(&input, e.to_string()).to_compile_error();
+        return e.into_syn_error(&input).to_compile_error();
     }
     if container.is_enum() {
         get_de_enum_impl_block(container)
@@ -20,8 +21,9 @@ pub fn get_de_impl_block(input: DeriveInput) -> proc_macro2::TokenStream {
 }
 
 pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream {
+    // `$finish` decides what to do with the deserialized variant `__v`.
     macro_rules! children_branches {
-        ($attrs:expr, $b:expr) => {
+        ($attrs:expr, $b:expr, $finish:expr) => {
             container.enum_variants.iter().map(|v| {
                 if matches!(&v.ele_type, EleType::Text) {
                     return quote! {};
@@ -29,6 +31,7 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
                 let name = v.name.as_ref().expect("should have name");
                 let ty_opt = v.ty;
                 let ident = v.ident;
+                let finish = $finish;
                 if let Some(field_ty) = ty_opt {
                     let generic_info = crate::container::get_generics(field_ty);
 
@@ -36,22 +39,32 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
                         let inner_ty = generic_info.get_boxed().expect("Boxed type should have an inner type");
                         quote! {
                             #name => {
-                                let _r = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(#name, reader, $attrs, $b);
-                                return Self::#ident(Box::new(_r));
+                                let _r = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(#name, reader, $attrs, $b)?;
+                                let __v = Self::#ident(Box::new(_r));
+                                #finish
                             }
                         }
                     } else {
                         quote! {
                             #name => {
-                                let _r = <#field_ty as ::xmlserde::XmlDeserialize>::deserialize(#name, reader, $attrs, $b);
-                                return Self::#ident(_r);
+                                let _r = <#field_ty as ::xmlserde::XmlDeserialize>::deserialize(#name, reader, $attrs, $b)?;
+                                let __v = Self::#ident(_r);
+                                #finish
                             }
                         }
                     }
                 } else {
+                    // Skip the content of the unit variant element
                     quote! {
                         #name => {
-                            return Self::#ident;
+                            if !$b {
+                                reader.read_to_end_into(
+                                    ::xmlserde::quick_xml::name::QName(#name),
+                                    &mut Vec::new(),
+                                )?;
+                            }
+                            let __v = Self::#ident;
+                            #finish
                         }
                     }
                 }
@@ -65,19 +78,22 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
             return;
         }
 
-        if text_opt.is_some() {
-            panic!("should only have one `text` type")
-        }
-
         text_opt = Some(v.ty.expect("expect type"));
         text_ident = Some(v.ident);
     });
 
     let text_function = if let Some(text_ty) = text_opt {
         let ident = text_ident.expect("should have ident for text");
+        let field = ident.to_string();
         quote! {
-            fn __deserialize_from_text(s: &str) -> Option<Self> {
-                Some(Self::#ident(<#text_ty as ::xmlserde::XmlValue>::deserialize(s).unwrap()))
+            fn __deserialize_from_text(s: &str) -> Result<Option<Self>, ::xmlserde::XmlError> {
+                match <#text_ty as ::xmlserde::XmlValue>::deserialize(s) {
+                    Ok(v) => Ok(Some(Self::#ident(v))),
+                    Err(_) => Err(::xmlserde::XmlError::DeserializeValueFailed {
+                        field: #field,
+                        raw: s.to_string(),
+                    }),
+                }
             }
         }
     } else {
@@ -85,8 +101,11 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
     };
     let ident = &container.original.ident;
     let (impl_generics, type_generics, where_clause) = container.original.generics.split_for_impl();
-    let event_start_branches = children_branches!(_s.attributes(), false);
-    let event_empty_branches = children_branches!(_s.attributes(), true);
+    // The variant is wrapped in the element `tag`, which is read to its end.
+    let event_start_branches =
+        children_branches!(_s.attributes(), false, quote! {result = Some(__v);});
+    let event_empty_branches =
+        children_branches!(_s.attributes(), true, quote! {result = Some(__v);});
     let children_tags = container
         .enum_variants
         .iter()
@@ -95,7 +114,7 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
             let name = v.name.as_ref().expect("should have `name` for `child`");
             quote! {#name}
         });
-    let exact_tags = children_branches!(attrs, is_empty);
+    let exact_tags = children_branches!(attrs, is_empty, quote! {return Ok(__v);});
     let get_roots = if !container.roots.is_empty() {
         let roots = container.get_root_names();
         quote! {
@@ -115,7 +134,7 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
                 reader: &mut ::xmlserde::quick_xml::Reader<B>,
                 attrs: ::xmlserde::quick_xml::events::attributes::Attributes,
                 is_empty: bool,
-            ) -> Self {
+            ) -> Result<Self, ::xmlserde::XmlError> {
                 use ::xmlserde::quick_xml::events::*;
                 match tag {
                     #(#exact_tags)*
@@ -136,12 +155,17 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
                             #(#event_empty_branches)*
                             _ => {},
                         }
-                        Ok(Event::Eof) => break,
-                        Err(_) => break,
+                        Ok(Event::Eof) => return Err(::xmlserde::XmlError::Eof),
+                        Err(e) => return Err(e.into()),
                         _ => {},
                     }
                 }
-                result.expect("did not find any tag")
+                result.ok_or_else(|| {
+                    ::xmlserde::XmlError::new(format!(
+                        "did not find any variant in '{}'",
+                        String::from_utf8_lossy(tag)
+                    ))
+                })
             }
 
             fn __get_children_tags() -> Vec<&'static [u8]> {
@@ -161,21 +185,40 @@ pub fn get_de_enum_impl_block(container: Container) -> proc_macro2::TokenStream
 }
 
 pub fn get_de_struct_impl_block(container: Container) -> proc_macro2::TokenStream {
-    let result = get_result(&container.struct_fields);
-    let summary = FieldsSummary::from_fields(&container.struct_fields);
+    // The `skip_deserializing` fields are left out of the parsing and keep their defaults
+    let (skipped, fields): (Vec<_>, Vec<_>) = container
+        .struct_fields
+        .iter()
+        .cloned()
+        .partition(|f| f.skip_deserializing);
+    let result = get_result(&container, &fields);
+    let skipped_result = get_skipped_result(&skipped);
+    let summary = FieldsSummary::from_fields(&fields);
     let fields_init = get_fields_init(&summary);
-    let result_untagged_structs = get_untagged_struct_fields_result(&summary.untagged_structs);
+    let body = get_body(&container, &fields, &skipped);
+    // A patch is read into the locals starting out empty and written back only after the whole
+    // element is read, so that a failure leaves `self` untouched
+    let patched = fields.iter().map(StructField::patched).collect::<Vec<_>>();
+    let merge_init = get_fields_merge_init(&FieldsSummary::from_fields(&patched));
+    let merge_body = get_body(&container, &patched, &skipped);
+    let merge_result = get_merge_result(&patched);
+    let derive_default = get_derive_default(&container);
     let FieldsSummary {
         children,
-        text,
         attrs,
         self_closed_children,
+        pis,
+        flattened,
+        attr_map,
         untagged_enums,
-        untagged_structs,
+        ..
     } = summary;
     let get_children_tags = if !children.is_empty() || !untagged_enums.is_empty() {
         let container = container.clone();
         let names = children.iter().map(|f| {
+            if let Some(wrapper) = &f.flatten_into {
+                return quote! {#wrapper};
+            }
             let n = container.get_field_name(f).unwrap_or_else(|| {
                 let ident = f
                     .original
@@ -192,7 +235,7 @@ pub fn get_de_struct_impl_block(container: Container) -> proc_macro2::TokenStrea
                 | Generic::Vec(t) => t,
                 | Generic::Opt(t) => t,
                 | Generic::Boxed(t) => t,
-                | Generic::None => &f.original.ty,
+                | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => &f.original.ty,
             };
             quote! {#ty::__get_children_tags()}
         });
@@ -208,39 +251,22 @@ pub fn get_de_struct_impl_block(container: Container) -> proc_macro2::TokenStrea
     };
     let attr_len = attrs.len();
     let sfc_len = self_closed_children.len();
-    let vec_init = get_vec_init(&children);
-    let attr_branches = attrs.into_iter().map(|a| get_attr_branch(&container, &a));
-    let child_branches =
-        children_match_branch(&container, &children, &untagged_enums, &untagged_structs);
-    let sfc_branch = sfc_match_branch(self_closed_children);
     let ident = &container.original.ident;
     let (impl_generics, type_generics, where_clause) = container.original.generics.split_for_impl();
-    let text_branch = text.map(text_match_branch);
     // Only those structs with only children can be untagged
-    let deserialize_from_unparsed =
-        if !children.is_empty() && attr_len == 0 && sfc_len == 0 && untagged_enums.is_empty() {
-            get_deserialize_from_unparsed(&children)
-        } else {
-            quote! {}
-        };
-    let encounter_unknown = if container.deny_unknown {
-        quote! {
-            let _field = std::str::from_utf8(_field).unwrap();
-            panic!("encoutnering unknown field: {:#?}", _field)
-        }
+    let deserialize_from_unparsed = if !children.is_empty()
+        && attr_len == 0
+        && sfc_len == 0
+        && pis.is_empty()
+        && flattened.is_empty()
+        && attr_map.is_none()
+        && untagged_enums.is_empty()
+        && children.iter().all(|c| c.key_attr.is_none())
+    {
+        get_deserialize_from_unparsed(&container, &children)
     } else {
         quote! {}
     };
-    let encounter_unknown_branch = quote! {
-        Ok(Event::Empty(_s)) => {
-            let _field = _s.name().into_inner();
-            #encounter_unknown
-        }
-        Ok(Event::Start(_s)) => {
-            let _field = _s.name().into_inner();
-            #encounter_unknown
-        }
-    };
 
     // Get the transformed root names for comparison
     let root_names = container.get_root_names();
@@ -249,12 +275,12 @@ pub fn get_de_struct_impl_block(container: Container) -> proc_macro2::TokenStrea
         let root_names_refs: Vec<_> = root_names.iter().collect();
         quote! {
             let valid_roots: &[&[u8]] = &[#(#root_names_refs),*];
-            if !valid_roots.iter().any(|r| r.eq_ignore_ascii_case(tag)) {
-                let valid_roots_str: Vec<String> = valid_roots.iter()
+            let transformed_tag = <Self as ::xmlserde::XmlDeserialize>::rename_all().transform(tag);
+            if !valid_roots.iter().any(|r| r.eq_ignore_ascii_case(tag) || *r == transformed_tag.as_slice()) {
+                let expected = valid_roots.iter()
                     .map(|r| String::from_utf8_lossy(r).to_string())
                     .collect();
-                let received_tag = String::from_utf8_lossy(tag).to_string();
-                panic!("Expected one of root tags {:?}, got {:?}", valid_roots_str, received_tag);
+                return Err(::xmlserde::XmlError::MissingRoot { expected });
             }
         }
     } else {
@@ -281,49 +307,345 @@ pub fn get_de_struct_impl_block(container: Container) -> proc_macro2::TokenStrea
                 reader: &mut ::xmlserde::quick_xml::Reader<B>,
                 attrs: ::xmlserde::quick_xml::events::attributes::Attributes,
                 is_empty: bool,
-            ) -> Self {
+            ) -> Result<Self, ::xmlserde::XmlError> {
                 #root_comparison
                 #fields_init
-                attrs.into_iter().for_each(|attr| {
-                    if let Ok(attr) = attr {
-                        match attr.key.into_inner() {
-                            #(#attr_branches)*
-                            _ => {
-                                let _field = attr.key.into_inner();
-                                #encounter_unknown;
-                            },
-                        }
-                    }
-                });
-                let mut buf = Vec::<u8>::new();
-                use ::xmlserde::quick_xml::events::Event;
-                #vec_init
-                if is_empty {} else {
-                    loop {
-                        match reader.read_event_into(&mut buf) {
-                            Ok(Event::End(e)) if e.name().into_inner() == tag => {
-                                break
-                            },
-                            #sfc_branch
-                            #child_branches
-                            #text_branch
-                            #encounter_unknown_branch
-                            Ok(Event::Eof) => break,
-                            Err(_) => break,
-                            _ => {},
-                        }
-                    }
-                }
-                #result_untagged_structs
-                Self {
+                #body
+                Ok(Self {
                     #result
-                }
+                    #skipped_result
+                })
+            }
+
+            fn __deserialize_into<B: std::io::BufRead>(
+                &mut self,
+                tag: &[u8],
+                reader: &mut ::xmlserde::quick_xml::Reader<B>,
+                attrs: ::xmlserde::quick_xml::events::attributes::Attributes,
+                is_empty: bool,
+            ) -> Result<(), ::xmlserde::XmlError> {
+                #root_comparison
+                #merge_init
+                #merge_body
+                #merge_result
+                Ok(())
             }
             #get_roots
             #rename_all
             #get_children_tags
             #deserialize_from_unparsed
         }
+        #derive_default
+    }
+}
+
+/// Reads the attributes and the children of the element into the locals declared by
+/// `get_fields_init` or `get_fields_merge_init`.
+fn get_body(
+    container: &Container,
+    fields: &[StructField],
+    skipped: &[StructField],
+) -> proc_macro2::TokenStream {
+    let summary = FieldsSummary::from_fields(fields);
+    let result_untagged_structs = get_untagged_struct_fields_result(&summary.untagged_structs);
+    let check_required = get_required_check(container, fields);
+    let FieldsSummary {
+        children,
+        text,
+        attrs,
+        self_closed_children,
+        pis,
+        flattened,
+        attr_map,
+        untagged_enums,
+        untagged_structs,
+    } = summary;
+    let saturating_checks = get_saturating_checks(&attrs);
+    let vec_fields = children
+        .iter()
+        .chain(untagged_enums.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    let vec_init = get_vec_init(&vec_fields);
+    let attr_branches = attrs.into_iter().map(|a| get_attr_branch(container, &a));
+    let child_branches =
+        children_match_branch(container, &children, &untagged_enums, &untagged_structs);
+    let sfc_branch = sfc_match_branch(self_closed_children);
+    let pi_branch = pi_match_branch(&pis);
+    let (cdata_init, cdata_result) = cdata_init_and_result(text.as_ref());
+    let text_branch = text.as_ref().map(text_match_branch);
+    // The tags of the `skip_deserializing` fields are known even though they are not read
+    let skipped_names = skipped
+        .iter()
+        .filter(|f| !matches!(f.ty, EleType::Skip))
+        .filter_map(|f| container.get_field_name(f))
+        .collect::<Vec<_>>();
+    let skipped_names = if skipped_names.is_empty() {
+        quote! {}
+    } else {
+        quote! {|| [#(&#skipped_names[..]),*].contains(&_field)}
+    };
+    let encounter_unknown_attr = if !flattened.is_empty() || attr_map.is_some() {
+        // The unknown attributes are left to the `flatten` and `attr_map` fields
+        let flatten = if flattened.is_empty() {
+            quote! {}
+        } else {
+            quote! {__flatten_attrs.push((_field.to_vec(), attr.value.to_vec()));}
+        };
+        let attr_map = attr_map.as_ref().map(|f| {
+            let ident = f.original.ident.as_ref().unwrap();
+            // The declarations written by `with_ns` and `with_custom_ns` are not collected
+            let skip_ns = if container.with_ns.is_some() || !container.custom_ns.is_empty() {
+                quote! {if attr.key.as_namespace_binding().is_none()}
+            } else {
+                quote! {}
+            };
+            quote! {
+                #skip_ns {
+                    #ident.insert(
+                        String::from_utf8_lossy(_field).into_owned(),
+                        String::from_utf8_lossy(&attr.value).into_owned(),
+                    );
+                }
+            }
+        });
+        quote! {
+            #flatten
+            #attr_map
+        }
+    } else if container.deny_unknown_attrs {
+        quote! {
+            if ::xmlserde::__ignore_unknown_fields() #skipped_names {
+                ::xmlserde::__warn_unknown("attribute", _field, tag)
+            } else {
+                let _field = String::from_utf8_lossy(_field).into_owned();
+                return Err(::xmlserde::XmlError::UnknownField(_field))
+            }
+        }
+    } else {
+        quote! {
+            ::xmlserde::__warn_unknown("attribute", _field, tag)
+        }
+    };
+    let encounter_unknown_child = if container.deny_unknown_children {
+        quote! {
+            if ::xmlserde::__ignore_unknown_fields() #skipped_names {
+                ::xmlserde::__warn_unknown("child", _field, tag);
+            } else {
+                let _field = String::from_utf8_lossy(_field).into_owned();
+                return Err(::xmlserde::XmlError::UnknownField(_field))
+            }
+        }
+    } else {
+        quote! {
+            ::xmlserde::__warn_unknown("child", _field, tag);
+        }
+    };
+    let encounter_unknown_branch = quote! {
+        Ok(Event::Empty(_s)) => {
+            let _field = _s.name().into_inner();
+            #encounter_unknown_child
+        }
+        Ok(Event::Start(_s)) => {
+            let _field = _s.name().into_inner();
+            #encounter_unknown_child
+        }
+    };
+
+    // Collect the namespace declarations for the `preserve_ns` children
+    let (ns_decls_init, collect_ns_decls) = if children.iter().any(|c| c.preserve_ns) {
+        (
+            quote! {
+                let mut __ns_decls = Vec::<(String, String)>::new();
+            },
+            quote! {
+                if attr.key.as_namespace_binding().is_some() {
+                    __ns_decls.push((
+                        String::from_utf8_lossy(attr.key.into_inner()).into_owned(),
+                        String::from_utf8_lossy(&attr.value).into_owned(),
+                    ));
+                }
+            },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    let (counters_init, check_cardinality) = get_cardinality_check(container, &children);
+
+    let (flatten_init, flatten_result) = get_flatten_result(&flattened);
+
+    quote! {
+        #saturating_checks
+        #ns_decls_init
+        #counters_init
+        #flatten_init
+        for attr in attrs {
+            if let Ok(attr) = attr {
+                #collect_ns_decls
+                match attr.key.into_inner() {
+                    #(#attr_branches)*
+                    _ => {
+                        let _field = attr.key.into_inner();
+                        #encounter_unknown_attr;
+                    },
+                }
+            }
+        }
+        #flatten_result
+        let mut buf = Vec::<u8>::new();
+        use ::xmlserde::quick_xml::events::Event;
+        #vec_init
+        #cdata_init
+        if is_empty {} else {
+            loop {
+                match reader.read_event_into(&mut buf) {
+                    Ok(Event::End(e)) if e.name().into_inner() == tag => {
+                        break
+                    },
+                    #sfc_branch
+                    #pi_branch
+                    #child_branches
+                    #text_branch
+                    #encounter_unknown_branch
+                    Ok(Event::Eof) => return Err(::xmlserde::XmlError::Eof),
+                    Err(e) => return Err(e.into()),
+                    _ => {},
+                }
+            }
+        }
+        #cdata_result
+        #result_untagged_structs
+        #check_required
+        #check_cardinality
+    }
+}
+
+/// Generates a `Default` impl using the values of the missing fields in deserializing.
+fn get_derive_default(container: &Container) -> proc_macro2::TokenStream {
+    if !container.derive_default {
+        return quote! {};
+    }
+    let fields = container.struct_fields.iter().map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        match (&f.default, &f.generic) {
+            | (Some(p), _) => quote! {#ident: #p(),},
+            | (None, Generic::Res(..)) => quote! {#ident: Err(::std::default::Default::default()),},
+            | _ => quote! {#ident: ::std::default::Default::default(),},
+        }
+    });
+    let ident = &container.original.ident;
+    let (impl_generics, type_generics, where_clause) = container.original.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::std::default::Default for #ident #type_generics #where_clause {
+            fn default() -> Self {
+                Self {
+                    #(#fields)*
+                }
+            }
+        }
+    }
+}
+
+/// Collects the attributes no other field takes and deserializes the `flatten` fields from them.
+fn get_flatten_result(
+    fields: &[StructField],
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    if fields.is_empty() {
+        return (quote! {}, quote! {});
+    }
+    let results = fields.iter().map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        let ty = &f.original.ty;
+        quote! {
+            #ident = Some(::xmlserde::__deserialize_flattened::<#ty>(tag, &__flatten_attrs)?);
+        }
+    });
+    (
+        quote! {let mut __flatten_attrs = Vec::<(Vec<u8>, Vec<u8>)>::new();},
+        quote! {#(#results)*},
+    )
+}
+
+fn get_required_check(container: &Container, fields: &[StructField]) -> proc_macro2::TokenStream {
+    let checks = fields.iter().filter(|f| f.required).map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        let missing = missing_field(container, f);
+        quote! {
+            if #ident.is_none() {
+                return Err(#missing);
+            }
+        }
+    });
+    quote! {#(#checks)*}
+}
+
+fn cardinality_counter(f: &StructField) -> syn::Ident {
+    format_ident!("__{}_count", f.original.ident.as_ref().unwrap())
+}
+
+/// Counts the occurrences of the `child` fields having `at_most` or `at_least`, and checks the
+/// counts once the element is read.
+fn get_cardinality_check(
+    container: &Container,
+    fields: &[StructField],
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let fields = fields
+        .iter()
+        .filter(|f| f.at_most.is_some() || f.at_least.is_some())
+        .collect::<Vec<_>>();
+    let inits = fields.iter().map(|f| {
+        let counter = cardinality_counter(f);
+        quote! {let mut #counter = 0usize;}
+    });
+    let checks = fields.iter().map(|f| {
+        let counter = cardinality_counter(f);
+        let name = container
+            .get_field_name(f)
+            .map(|n| String::from_utf8_lossy(&n.value()).into_owned())
+            .unwrap_or_default();
+        let at_most = f.at_most.as_ref().map(|n| {
+            let msg = format!("expected at most {} '{}' element(s)", n, name);
+            quote! {
+                if #counter > #n {
+                    return Err(::xmlserde::XmlError::new(format!("{}, found {}", #msg, #counter)));
+                }
+            }
+        });
+        let at_least = f.at_least.as_ref().map(|n| {
+            let msg = format!("expected at least {} '{}' element(s)", n, name);
+            quote! {
+                if #counter < #n {
+                    return Err(::xmlserde::XmlError::new(format!("{}, found {}", #msg, #counter)));
+                }
+            }
+        });
+        quote! {
+            #at_most
+            #at_least
+        }
+    });
+    (quote! {#(#inits)*}, quote! {#(#checks)*})
+}
+
+/// Asserts that the `T` of the `Saturating<T>` attributes implements `XmlValue`, so that the
+/// error points at the field rather than at the derive.
+fn get_saturating_checks(attrs: &[StructField]) -> proc_macro2::TokenStream {
+    let checks = attrs
+        .iter()
+        .filter_map(|f| f.saturating_inner())
+        .map(|inner| {
+            quote_spanned! {inner.span()=>
+                __assert_xml_value::<#inner>();
+            }
+        })
+        .collect::<Vec<_>>();
+    if checks.is_empty() {
+        return quote! {};
+    }
+    quote! {
+        fn __assert_xml_value<T: ::xmlserde::XmlValue>() {}
+        #(#checks)*
     }
 }
 
@@ -337,17 +659,17 @@ fn get_untagged_struct_fields_result(fileds: &[StructField]) -> proc_macro2::Tok
       | Generic::Vec(_) => unreachable!(),
       | Generic::Opt(_t) => quote! {
           if #ident_opt_unparsed_array.len() > 0 {
-              #ident = Some(#_t::__deserialize_from_unparsed_array(#ident_opt_unparsed_array));
+              #ident = Some(#_t::__deserialize_from_unparsed_array(#ident_opt_unparsed_array)?);
           }
       },
       | Generic::Boxed(inner_ty) => quote! {
           if #ident_unparsed_array.len() > 0 {
-              #ident = Some(Box::new(#inner_ty::__deserialize_from_unparsed_array(#ident_unparsed_array)));
+              #ident = Some(Box::new(#inner_ty::__deserialize_from_unparsed_array(#ident_unparsed_array)?));
           }
       },
-      | Generic::None => quote! {
+      | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => quote! {
           if #ident_unparsed_array.len() > 0 {
-              #ident = Some(#ty::__deserialize_from_unparsed_array(#ident_unparsed_array));
+              #ident = Some(#ty::__deserialize_from_unparsed_array(#ident_unparsed_array)?);
           }
       },
     }
@@ -356,12 +678,18 @@ fn get_untagged_struct_fields_result(fileds: &[StructField]) -> proc_macro2::Tok
     quote! {#(#branch)*}
 }
 
-fn get_result(fields: &[StructField]) -> proc_macro2::TokenStream {
+fn get_result(container: &Container, fields: &[StructField]) -> proc_macro2::TokenStream {
     let branch = fields.iter().map(|f| {
         let ident = f.original.ident.as_ref().unwrap();
         if f.is_required() {
+            let missing = missing_field(container, f);
+            quote! {
+                #ident: #ident.ok_or_else(|| #missing)?,
+            }
+        } else if f.is_collected() {
+            let collected = f.collect_from_vec(quote! {#ident});
             quote! {
-                #ident: #ident.unwrap(),
+                #ident: #collected,
             }
         } else {
             quote! {
@@ -372,25 +700,66 @@ fn get_result(fields: &[StructField]) -> proc_macro2::TokenStream {
     quote! {#(#branch)*}
 }
 
-fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
-    let attrs_inits = fields.attrs.iter().map(|f| {
+/// The error of a field missing in the element, named after its tag if it has one.
+fn missing_field(container: &Container, f: &StructField) -> proc_macro2::TokenStream {
+    let element = container.original.ident.to_string();
+    let field = field_label(container, f);
+    quote! {
+        ::xmlserde::XmlError::MissingRequiredField {
+            element: #element,
+            field: #field,
+        }
+    }
+}
+
+/// The name of the field in the errors: its tag if it has one, or else its identifier.
+fn field_label(container: &Container, f: &StructField) -> String {
+    container
+        .get_field_name(f)
+        .map(|n| String::from_utf8_lossy(&n.value()).into_owned())
+        .unwrap_or_else(|| f.original.ident.as_ref().unwrap().to_string())
+}
+
+/// Uses the `default` function or the `Default` impl for the `skip_deserializing` fields. The
+/// missing `Default` impl is reported at the type of the field.
+fn get_skipped_result(fields: &[StructField]) -> proc_macro2::TokenStream {
+    let branch = fields.iter().map(|f| {
         let ident = f.original.ident.as_ref().unwrap();
         let ty = &f.original.ty;
         match &f.default {
-            | Some(p) => {
-                quote! {let mut #ident = #p();}
-            },
-            | None => {
-                if let Some(opt) = f.generic.get_opt() {
-                    quote! {
-                        let mut #ident = Option::<#opt>::None;
-                    }
-                } else {
-                    quote! {let mut #ident = Option::<#ty>::None;}
-                }
+            | Some(p) => quote! {#ident: #p(),},
+            | None => quote_spanned! {ty.span()=>
+                #ident: <#ty as ::std::default::Default>::default(),
             },
         }
     });
+    quote! {#(#branch)*}
+}
+
+fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
+    let attrs_inits = fields
+        .attrs
+        .iter()
+        .chain(fields.pis.iter())
+        .chain(fields.flattened.iter())
+        .map(|f| {
+            let ident = f.original.ident.as_ref().unwrap();
+            let ty = &f.original.ty;
+            match &f.default {
+                | Some(p) => {
+                    quote! {let mut #ident = #p();}
+                },
+                | None => {
+                    if let Some(opt) = f.generic.get_opt() {
+                        quote! {
+                            let mut #ident = Option::<#opt>::None;
+                        }
+                    } else {
+                        quote! {let mut #ident = Option::<#ty>::None;}
+                    }
+                },
+            }
+        });
     let children_inits = fields.children.iter().map(|f| {
         let ident = f.original.ident.as_ref().unwrap();
         let ty = &f.original.ty;
@@ -401,7 +770,8 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
                 }
             },
             | None => match f.generic {
-                | Generic::Vec(v) => {
+                | Generic::Vec(_) => {
+                    let v = f.vec_elem_type().unwrap();
                     quote! {
                         let mut #ident = Vec::<#v>::new();
                     }
@@ -416,6 +786,16 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
                         let mut #ident = Option::<Box<#inner_ty>>::None;
                     }
                 },
+                | Generic::BoxedOpt(_) => {
+                    quote! {
+                        let mut #ident: #ty = Box::new(None);
+                    }
+                },
+                | Generic::Res(_, err) => {
+                    quote! {
+                        let mut #ident: #ty = Err(<#err as ::std::default::Default>::default());
+                    }
+                },
                 | Generic::None => {
                     quote! {
                         let mut #ident = Option::<#ty>::None;
@@ -431,7 +811,7 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
                 | Generic::Vec(_) => panic!("text element should not be Vec<T>"),
                 | Generic::Opt(t) => t,
                 | Generic::Boxed(t) => t,
-                | Generic::None => &f.original.ty,
+                | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => &f.original.ty,
             };
             // let ty = &f.original.ty;
             match &f.default {
@@ -449,6 +829,11 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
         },
         | None => quote! {},
     };
+    let attr_map_init = fields.attr_map.iter().map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        let ty = &f.original.ty;
+        quote! {let mut #ident = <#ty as ::std::default::Default>::default();}
+    });
     let sfc_init = fields.self_closed_children.iter().map(|f| {
         let ident = f.original.ident.as_ref().unwrap();
         quote! {
@@ -479,7 +864,7 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
                     let mut #ident = Option::<Box<#inner_ty>>::None;
                 }
             },
-            | Generic::None => {
+            | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
                 quote! {
                     let mut #ident = Option::<#ty>::None;
                 }
@@ -514,7 +899,7 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
                     let mut #ident_unparsed_array = Vec::new();
                 }
             },
-            | Generic::None => {
+            | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
                 quote! {
                     let mut #ident = Option::<#ty>::None;
                     let mut #ident_unparsed_array = Vec::new();
@@ -524,6 +909,7 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
     });
     quote! {
         #(#attrs_inits)*
+        #(#attr_map_init)*
         #(#sfc_init)*
         #(#children_inits)*
         #text_init
@@ -532,7 +918,120 @@ fn get_fields_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
     }
 }
 
-fn get_deserialize_from_unparsed(children: &[StructField]) -> proc_macro2::TokenStream {
+/// How a field is merged back into `self` in `__deserialize_into`.
+///
+/// It follows the local variables declared in `get_fields_init` for the `patched` fields so that
+/// the match branches can be shared between `deserialize` and `__deserialize_into`.
+enum MergeKind {
+    /// The local is a copy of the field, like the `bool` of a `sfc` field.
+    Keep,
+    /// The local is a `Vec` which replaces the field only if it is not empty.
+    Vec,
+    /// The local is a map whose entries are added to the field.
+    Extend,
+    /// The local has the same type as the field and replaces it only if it is `Some`.
+    Opt,
+    /// The local is an `Option<T>` which replaces the field only if it is `Some`.
+    Required,
+}
+
+fn get_merge_kind(f: &StructField) -> MergeKind {
+    if matches!(f.ty, EleType::SelfClosedChild) {
+        return MergeKind::Keep;
+    }
+    if matches!(f.ty, EleType::AttrMap) {
+        return MergeKind::Extend;
+    }
+    if f.generic.is_vec() && !matches!(f.ty, EleType::Attr) {
+        return MergeKind::Vec;
+    }
+    if f.generic.is_opt() || matches!(f.generic, Generic::BoxedOpt(_)) {
+        return MergeKind::Opt;
+    }
+    MergeKind::Required
+}
+
+fn get_fields_merge_init(fields: &FieldsSummary) -> proc_macro2::TokenStream {
+    let all = fields
+        .attrs
+        .iter()
+        .chain(fields.self_closed_children.iter())
+        .chain(fields.pis.iter())
+        .chain(fields.flattened.iter())
+        .chain(fields.attr_map.iter())
+        .chain(fields.children.iter())
+        .chain(fields.text.iter())
+        .chain(fields.untagged_enums.iter())
+        .chain(fields.untagged_structs.iter());
+    let inits = all.map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        let ty = &f.original.ty;
+        let init = match get_merge_kind(f) {
+            | MergeKind::Keep => quote! {let mut #ident = self.#ident;},
+            | MergeKind::Vec => {
+                let t = f.vec_elem_type().unwrap();
+                quote! {let mut #ident = Vec::<#t>::new();}
+            },
+            | MergeKind::Extend => quote! {
+                let mut #ident = <#ty as ::std::default::Default>::default();
+            },
+            | MergeKind::Opt if f.generic.is_opt() => quote! {let mut #ident: #ty = None;},
+            | MergeKind::Opt => quote! {let mut #ident: #ty = Box::new(None);},
+            | MergeKind::Required => quote! {let mut #ident = Option::<#ty>::None;},
+        };
+        if !matches!(f.ty, EleType::UntaggedStruct) {
+            return init;
+        }
+        let unparsed_array = if f.generic.is_opt() {
+            format_ident!("{}_opt_unparseds", ident)
+        } else {
+            format_ident!("{}_unparseds", ident)
+        };
+        quote! {
+            #init
+            let mut #unparsed_array = Vec::new();
+        }
+    });
+    quote! {#(#inits)*}
+}
+
+fn get_merge_result(fields: &[StructField]) -> proc_macro2::TokenStream {
+    let branch = fields.iter().map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        match get_merge_kind(f) {
+            | MergeKind::Keep => quote! {
+                self.#ident = #ident;
+            },
+            | MergeKind::Vec => {
+                let collected = f.collect_from_vec(quote! {#ident});
+                quote! {
+                    if !#ident.is_empty() {
+                        self.#ident = #collected;
+                    }
+                }
+            },
+            | MergeKind::Extend => quote! {
+                self.#ident.extend(#ident);
+            },
+            | MergeKind::Opt => quote! {
+                if #ident.is_some() {
+                    self.#ident = #ident;
+                }
+            },
+            | MergeKind::Required => quote! {
+                if let Some(__v) = #ident {
+                    self.#ident = __v;
+                }
+            },
+        }
+    });
+    quote! {#(#branch)*}
+}
+
+fn get_deserialize_from_unparsed(
+    container: &Container,
+    children: &[StructField],
+) -> proc_macro2::TokenStream {
     let init = children.iter().map(|c| {
         let ident = c.original.ident.as_ref().unwrap();
         if let Some(path) = &c.default {
@@ -544,6 +1043,10 @@ fn get_deserialize_from_unparsed(children: &[StructField]) -> proc_macro2::Token
             | Generic::Vec(_) => quote! {let mut #ident = vec![];},
             | Generic::Opt(_) => quote! {let mut #ident = None;},
             | Generic::Boxed(_) => quote! {let mut #ident = None;},
+            | Generic::BoxedOpt(_) => quote! {let mut #ident = Box::new(None);},
+            | Generic::Res(..) => {
+                quote! {let mut #ident = Err(::std::default::Default::default());}
+            },
             | Generic::None => quote! {let mut #ident = None;},
         }
     });
@@ -555,35 +1058,42 @@ fn get_deserialize_from_unparsed(children: &[StructField]) -> proc_macro2::Token
             | Generic::Vec(t) => {
                 quote! {
                     #name => {
-                        #ident.push(content.deserialize_to::<#t>().unwrap());
+                        #ident.push(content.deserialize_to::<#t>()?);
                     }
                 }
             },
             | Generic::Opt(t) => {
                 quote! {
                     #name => {
-                        #ident = Some(content.deserialize_to::<#t>().unwrap());
+                        #ident = Some(content.deserialize_to::<#t>()?);
                     }
                 }
             },
             | Generic::Boxed(t) => {
                 quote! {
                     #name => {
-                        #ident = Some(Box::new(content.deserialize_to::<#t>().unwrap()));
+                        #ident = Some(Box::new(content.deserialize_to::<#t>()?));
+                    }
+                }
+            },
+            | Generic::BoxedOpt(t) => {
+                quote! {
+                    #name => {
+                        #ident = Box::new(Some(content.deserialize_to::<#t>()?));
                     }
                 }
             },
-            | Generic::None => {
-                if c.default.is_some() {
+            | Generic::Res(..) | Generic::None => {
+                if c.default.is_some() || matches!(c.generic, Generic::Res(..)) {
                     quote! {
                         #name => {
-                            #ident = content.deserialize_to::<#original_type>().unwrap();
+                            #ident = content.deserialize_to::<#original_type>()?;
                         }
                     }
                 } else {
                     quote! {
                         #name => {
-                            #ident = Some(content.deserialize_to::<#original_type>().unwrap());
+                            #ident = Some(content.deserialize_to::<#original_type>()?);
                         }
                     }
                 }
@@ -594,8 +1104,14 @@ fn get_deserialize_from_unparsed(children: &[StructField]) -> proc_macro2::Token
         let idents = children.iter().map(|c| {
             let ident = c.original.ident.as_ref().unwrap();
             if c.is_required() {
+                let missing = missing_field(container, c);
+                quote! {
+                    #ident: #ident.ok_or_else(|| #missing)?
+                }
+            } else if c.is_collected() {
+                let collected = c.collect_from_vec(quote! {#ident});
                 quote! {
-                    #ident: #ident.expect("missing field")
+                    #ident: #collected
                 }
             } else {
                 quote! {
@@ -604,20 +1120,22 @@ fn get_deserialize_from_unparsed(children: &[StructField]) -> proc_macro2::Token
             }
         });
         quote! {
-            Self {
+            Ok(Self {
                 #(#idents),*
-            }
+            })
         }
     };
     quote! {
-        fn __deserialize_from_unparsed_array(array: Vec<(&'static [u8], ::xmlserde::Unparsed)>) -> Self {
+        fn __deserialize_from_unparsed_array(
+            array: Vec<(&'static [u8], ::xmlserde::Unparsed)>,
+        ) -> Result<Self, ::xmlserde::XmlError> {
             #(#init)*
-            array.into_iter().for_each(|(tag, content)| {
+            for (tag, content) in array {
                 match tag {
                     #(#body),*
                     _ => {},
                 }
-            });
+            }
             #result
         }
     }
@@ -629,9 +1147,11 @@ fn get_vec_init(children: &[StructField]) -> proc_macro2::TokenStream {
         .filter(|c| c.generic.is_vec())
         .map(|c| match &c.vec_size {
             | Some(lit) => {
-                let vec_ty = &c.generic.get_vec().unwrap();
+                let vec_ty = c.vec_elem_type().unwrap();
                 let ident = c.original.ident.as_ref().unwrap();
                 match lit {
+                    // The size read by a patch may be missing, so the capacity is left to grow
+                    | syn::Lit::Str(_) if c.patching => quote! {},
                     | syn::Lit::Str(s) => {
                         let path = container::parse_lit_str::<syn::Expr>(s).unwrap();
                         quote! {
@@ -677,6 +1197,31 @@ fn sfc_match_branch(fields: Vec<StructField>) -> proc_macro2::TokenStream {
     }
 }
 
+/// Reads the data of the processing instructions whose target is the `name` of a `pi` field.
+fn pi_match_branch(fields: &[StructField]) -> proc_macro2::TokenStream {
+    let branches = fields.iter().map(|f| {
+        let target = f.name.as_ref().expect("`pi` field should have a name");
+        let ident = f.original.ident.as_ref().unwrap();
+        let t = f.generic.get_opt().expect("`pi` field should be Option<T>");
+        let field = String::from_utf8_lossy(&target.value()).into_owned();
+        quote! {
+            Ok(Event::PI(__p)) if __p.target() == #target => {
+                let __data = String::from_utf8_lossy(__p.content());
+                match <#t as ::xmlserde::XmlValue>::deserialize(__data.trim()) {
+                    Ok(__v) => #ident = Some(__v),
+                    Err(_) => {
+                        return Err(::xmlserde::XmlError::DeserializeValueFailed {
+                            field: #field,
+                            raw: __data.trim().to_string(),
+                        })
+                    },
+                }
+            },
+        }
+    });
+    quote! {#(#branches)*}
+}
+
 fn get_attr_branch(container: &Container, field: &StructField) -> proc_macro2::TokenStream {
     let ident = field.original.ident.as_ref().unwrap();
     let t = &field.original.ty;
@@ -704,18 +1249,45 @@ fn get_attr_branch(container: &Container, field: &StructField) -> proc_macro2::T
         Vec::new()
     };
 
+    let field_name = String::from_utf8_lossy(&tag.value()).into_owned();
+    let on_error = |raw: proc_macro2::TokenStream| match field.error_mode {
+        | ErrorMode::Panic => quote! {
+            return Err(::xmlserde::XmlError::DeserializeValueFailed {
+                field: #field_name,
+                raw: #raw.to_string(),
+            })
+        },
+        | ErrorMode::Ignore => quote! {},
+        | ErrorMode::Default => {
+            let value = match &field.default {
+                | Some(p) => quote! {#p()},
+                | None => quote! {<#t as ::std::default::Default>::default()},
+            };
+            if field.is_required() {
+                quote! {#ident = Some(#value);}
+            } else {
+                quote! {#ident = #value;}
+            }
+        },
+    };
+
     if field.generic.is_opt() {
         let opt_ty = field.generic.get_opt().unwrap();
+        let on_error = on_error(quote! {s});
+        let deserialize = match &field.deserialize_with {
+            | Some(f) => quote! {#f},
+            | None => quote! {<#opt_ty as ::xmlserde::XmlValue>::deserialize},
+        };
         quote! {
             #tag #(| #mapped_tags)* | _ if attr.key.into_inner().eq_ignore_ascii_case(#tag) #(| attr.key.into_inner().eq_ignore_ascii_case(#mapped_tags))* => {
                 use xmlserde::{XmlValue, XmlDeserialize};
-                let s = String::from_utf8(attr.value.into_iter().map(|c| *c).collect()).unwrap();
-                match #opt_ty::deserialize(&s) {
+                let s = String::from_utf8_lossy(&attr.value);
+                match #deserialize(&s) {
                     Ok(__v) => {
                         #ident = Some(__v);
                     },
                     Err(_) => {
-                        panic!("deserialize failed in attr opt")
+                        #on_error
                     },
                 }
             }
@@ -726,16 +1298,21 @@ fn get_attr_branch(container: &Container, field: &StructField) -> proc_macro2::T
         } else {
             quote! {#ident = __v;}
         };
+        let on_error = on_error(quote! {__s});
+        let deserialize = match &field.deserialize_with {
+            | Some(f) => quote! {#f},
+            | None => quote! {<#t as ::xmlserde::XmlValue>::deserialize},
+        };
         quote! {
             #tag #(| #mapped_tags)* | _ if attr.key.into_inner().eq_ignore_ascii_case(#tag) #(| attr.key.into_inner().eq_ignore_ascii_case(#mapped_tags))* => {
                 use xmlserde::{XmlValue, XmlDeserialize};
-                let __s = String::from_utf8(attr.value.into_iter().map(|c| *c).collect()).unwrap();
-                match #t::deserialize(&__s) {
+                let __s = String::from_utf8_lossy(&attr.value);
+                match #deserialize(&__s) {
                     Ok(__v) => {
                         #tt
                     },
                     Err(_) => {
-                        panic!("deserialize failed in attr")
+                        #on_error
                     },
                 }
             }
@@ -743,37 +1320,198 @@ fn get_attr_branch(container: &Container, field: &StructField) -> proc_macro2::T
     }
 }
 
-fn text_match_branch(field: StructField) -> proc_macro2::TokenStream {
+fn text_match_branch(field: &StructField) -> proc_macro2::TokenStream {
+    let set_field = text_set_field(field);
+    if matches!(field.ty, EleType::CData) {
+        // The CDATA sections are collected and deserialized once the element ends, since a text
+        // containing `]]>` is split into several sections
+        return quote! {
+            Ok(Event::CData(__s)) => {
+                __cdata.get_or_insert_with(String::new).push_str(&String::from_utf8_lossy(&__s));
+            },
+            Ok(Event::Text(__s)) => {
+                __cdata.get_or_insert_with(String::new).push_str(&__s.unescape()?);
+            },
+        };
+    }
     if !matches!(field.ty, EleType::Text) {
         panic!("")
     }
+    quote! {
+        Ok(Event::Text(__s)) => {
+            let __r = __s.unescape()?;
+            #set_field
+        },
+    }
+}
+
+/// Declares the buffer of a `cdata` field and deserializes it after reading the element.
+fn cdata_init_and_result(
+    field: Option<&StructField>,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match field {
+        | Some(f) if matches!(f.ty, EleType::CData) => {
+            let set_field = text_set_field(f);
+            (
+                quote! {let mut __cdata = Option::<String>::None;},
+                quote! {
+                    if let Some(__r) = __cdata {
+                        #set_field
+                    }
+                },
+            )
+        },
+        | _ => (quote! {}, quote! {}),
+    }
+}
+
+/// Deserializes the text `__r` into the `text` or `cdata` field.
+fn text_set_field(field: &StructField) -> proc_macro2::TokenStream {
     let ident = field.original.ident.as_ref().expect("should have idnet");
+    let field_name = ident.to_string();
     // let t = &field.original.ty;
     let (t, is_opt) = match field.generic {
         | Generic::Vec(_) => panic!("text element should not be Vec<T>"),
         | Generic::Opt(ty) => (ty, true),
         | Generic::Boxed(t) => (t, true),
-        | Generic::None => (&field.original.ty, false),
+        | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => (&field.original.ty, false),
     };
     let tt = if field.is_required() || is_opt {
         quote! {#ident = Some(__v);}
     } else {
         quote! {#ident = __v;}
     };
+    let trim = if field.trim {
+        quote! {let __r = __r.trim();}
+    } else {
+        quote! {}
+    };
+    let deserialize = match &field.deserialize_with {
+        | Some(f) => quote! {#f},
+        | None => quote! {<#t as ::xmlserde::XmlValue>::deserialize},
+    };
     quote! {
-        Ok(Event::Text(__s)) => {
-            use ::xmlserde::{XmlValue, XmlDeserialize};
-            let __r = __s.unescape().unwrap();
-            match #t::deserialize(&__r) {
-                Ok(__v) => {
-                    // #ident = v;
-                    #tt
-                },
-                Err(_) => {
-                    panic!("deserialize failed in text element")
+        use ::xmlserde::{XmlValue, XmlDeserialize};
+        #trim
+        match #deserialize(&__r) {
+            Ok(__v) => {
+                // #ident = v;
+                #tt
+            },
+            Err(_) => {
+                return Err(::xmlserde::XmlError::DeserializeValueFailed {
+                    field: #field_name,
+                    raw: __r.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// Reads a child of a map with `key_attr` along with the key taken from its attribute.
+fn keyed_push(
+    f: &StructField,
+    key: &syn::LitByteStr,
+    tag: &syn::LitByteStr,
+    inherit_ns: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let ident = f.original.ident.as_ref().unwrap();
+    let vec_ty = f.generic.get_vec().unwrap();
+    let (key_ty, _) = container::get_map_types(&f.original.ty).unwrap();
+    let element = String::from_utf8_lossy(&tag.value()).into_owned();
+    let field = String::from_utf8_lossy(&key.value()).into_owned();
+    quote! {
+        let __raw = s
+            .attributes()
+            .flatten()
+            .find(|attr| attr.key.into_inner() == #key)
+            .map(|attr| String::from_utf8_lossy(&attr.value).into_owned())
+            .ok_or(::xmlserde::XmlError::MissingRequiredField {
+                element: #element,
+                field: #field,
+            })?;
+        let __key = match <#key_ty as ::xmlserde::XmlValue>::deserialize(&__raw) {
+            Ok(__k) => __k,
+            Err(_) => {
+                return Err(::xmlserde::XmlError::DeserializeValueFailed {
+                    field: #field,
+                    raw: __raw,
+                })
+            },
+        };
+        let __ele = <#vec_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+        #inherit_ns
+        #ident.push((__key, __ele));
+    }
+}
+
+/// Guards the `push` into a `Vec` field with its `limit`. Once the limit is reached, the
+/// element `tag` is skipped without being deserialized. An empty `tag` means a text.
+fn limit_push(
+    f: &StructField,
+    tag: proc_macro2::TokenStream,
+    push: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let Some(limit) = &f.limit else {
+        return push;
+    };
+    let ident = f.original.ident.as_ref().unwrap();
+    let skip = if tag.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            if !is_empty {
+                reader.read_to_end_into(::xmlserde::quick_xml::name::QName(#tag), &mut Vec::new())?;
+            }
+        }
+    };
+    quote! {
+        if #ident.len() >= #limit {
+            #skip
+        } else {
+            #push
+        }
+    }
+}
+
+/// Matches the `wrapper` element of a `flatten_into` field and runs `push` for each of its
+/// `item` children. The other children of the wrapper are skipped.
+fn flatten_into_branch(
+    wrapper: &syn::LitByteStr,
+    item: &syn::LitByteStr,
+    push: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    quote! {
+        _wrapper if _wrapper == #wrapper.as_ref() => {
+            if !is_empty {
+                let mut __buf = Vec::<u8>::new();
+                loop {
+                    match reader.read_event_into(&mut __buf) {
+                        Ok(Event::End(e)) if e.name().into_inner() == #wrapper.as_ref() => break,
+                        Ok(Event::Empty(s)) => {
+                            let is_empty = true;
+                            let _tag = s.name().into_inner();
+                            if _tag == #item.as_ref() {
+                                #push
+                            }
+                        }
+                        Ok(Event::Start(s)) => {
+                            let is_empty = false;
+                            let _tag = s.name().into_inner();
+                            if _tag == #item.as_ref() {
+                                #push
+                            } else {
+                                reader.read_to_end_into(s.name(), &mut Vec::new())?;
+                            }
+                        }
+                        Ok(Event::Eof) => return Err(::xmlserde::XmlError::Eof),
+                        Err(e) => return Err(e.into()),
+                        _ => {},
+                    }
+                    __buf.clear();
                 }
             }
-        },
+        }
     }
 }
 
@@ -788,29 +1526,30 @@ fn untag_text_enum_branches(untags: &[StructField]) -> proc_macro2::TokenStream
         let ty = &f.original.ty;
         let branch = match f.generic {
             | Generic::Vec(ty) => {
+                let push = limit_push(f, quote! {}, quote! {#ident.push(t);});
                 quote! {
-                    if let Some(t) = #ty::__deserialize_from_text(&_str) {
-                        #ident.push(t);
+                    if let Some(t) = #ty::__deserialize_from_text(&_str)? {
+                        #push
                     }
                 }
             },
             | Generic::Opt(ty) => {
                 quote! {
-                    if let Some(t) = #ty::__deserialize_from_text(&_str) {
+                    if let Some(t) = #ty::__deserialize_from_text(&_str)? {
                         #ident = Some(t);
                     }
                 }
             },
             | Generic::Boxed(inner_ty) => {
                 quote! {
-                    if let Some(t) = #inner_ty::__deserialize_from_text(&_str) {
+                    if let Some(t) = #inner_ty::__deserialize_from_text(&_str)? {
                         #ident = Some(Box::new(t));
                     }
                 }
             },
-            | Generic::None => {
+            | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
                 quote! {
-                    if let Some(t) = #ty::__deserialize_from_text(&_str) {
+                    if let Some(t) = #ty::__deserialize_from_text(&_str)? {
                         #ident = Some(t);
                     }
                 }
@@ -832,30 +1571,35 @@ fn untag_enums_match_branch(fields: &[StructField]) -> proc_macro2::TokenStream
         let ty = &f.original.ty;
         let branch = match f.generic {
             | Generic::Vec(ty) => {
+                let push = limit_push(
+                    f,
+                    quote! {_ty},
+                    quote! {#ident.push(#ty::deserialize(_ty, reader, s.attributes(), is_empty)?);},
+                );
                 quote! {
                     _ty if #ty::__get_children_tags().contains(&_ty) => {
-                        #ident.push(#ty::deserialize(_ty, reader, s.attributes(), is_empty));
+                        #push
                     }
                 }
             },
             | Generic::Opt(ty) => {
                 quote! {
                     _ty if #ty::__get_children_tags().contains(&_ty) => {
-                        #ident = Some(#ty::deserialize(_ty, reader, s.attributes(), is_empty));
+                        #ident = Some(#ty::deserialize(_ty, reader, s.attributes(), is_empty)?);
                     }
                 }
             },
             | Generic::Boxed(inner_ty) => {
                 quote! {
                     _ty if #inner_ty::__get_children_tags().contains(&_ty) => {
-                        #ident = Some(Box::new(#inner_ty::deserialize(_ty, reader, s.attributes(), is_empty)));
+                        #ident = Some(Box::new(#inner_ty::deserialize(_ty, reader, s.attributes(), is_empty)?));
                     }
                 }
             },
-            | Generic::None => {
+            | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
                 quote! {
                     _t if #ty::__get_children_tags().contains(&_t) => {
-                        #ident = Some(#ty::deserialize(_t, reader, s.attributes(), is_empty));
+                        #ident = Some(#ty::deserialize(_t, reader, s.attributes(), is_empty)?);
                     }
                 }
             },
@@ -883,7 +1627,7 @@ fn untag_structs_match_branch(fields: &[StructField]) -> proc_macro2::TokenStrea
       | Generic::Vec(_) => unreachable!(),
       | Generic::Opt(t) => quote! {
           _t if #t::__get_children_tags().contains(&_t) => {
-              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty);
+              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty)?;
               let _tags = #t::__get_children_tags();
               if !_tags.is_empty() {
                   let idx = _tags.binary_search(&_t).unwrap();
@@ -893,7 +1637,7 @@ fn untag_structs_match_branch(fields: &[StructField]) -> proc_macro2::TokenStrea
       },
       | Generic::Boxed(inner_ty) => quote! {
           _t if #inner_ty::__get_children_tags().contains(&_t) => {
-              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty);
+              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty)?;
               let _tags = #inner_ty::__get_children_tags();
               if !_tags.is_empty() {
                   let idx = _tags.binary_search(&_t).unwrap();
@@ -901,9 +1645,9 @@ fn untag_structs_match_branch(fields: &[StructField]) -> proc_macro2::TokenStrea
               }
           }
       },
-      | Generic::None => quote! {
+      | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => quote! {
           _t if #ty::__get_children_tags().contains(&_t) => {
-              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty);
+              let _r = ::xmlserde::Unparsed::deserialize(_t, reader, s.attributes(), is_empty)?;
               let _tags = #ty::__get_children_tags();
               if !_tags.is_empty() {
                   let idx = _tags.binary_search(&_t).unwrap();
@@ -935,6 +1679,7 @@ fn children_match_branch(
 
         let type_for_is_enum_check = match &f.generic {
             Generic::Opt(inner_ty) | Generic::Boxed(inner_ty) | Generic::Vec(inner_ty) => quote! { #inner_ty },
+            Generic::BoxedOpt(inner_ty) | Generic::Res(inner_ty, _) => quote! { #inner_ty },
             Generic::None => quote! { #t },
         };
 
@@ -943,56 +1688,124 @@ fn children_match_branch(
             panic!("No name or mapped_names for field: {} in children_match_branch", ident_str)
         });
 
+        let inherit_ns = |v: proc_macro2::TokenStream| {
+            if f.preserve_ns {
+                quote! {
+                    let mut #v = #v;
+                    #v.inherit_ns(&__ns_decls);
+                }
+            } else {
+                quote! {}
+            }
+        };
+        let ele_inherit_ns = inherit_ns(quote! {__ele});
+        let f_inherit_ns = inherit_ns(quote! {__f});
+        // A single child read before is overwritten unless `strict_duplicate_fields` is set
+        let check_dup = quote! {
+            if #ident.is_some() && ::xmlserde::__strict_duplicate_fields() {
+                let _tag = String::from_utf8_lossy(_tag).into_owned();
+                return Err(::xmlserde::XmlError::DuplicateField(_tag));
+            }
+        };
+
         let branch = match f.generic {
             Generic::Vec(ref vec_ty) => {
-                quote! {
-                    _tag if #type_for_is_enum_check::__is_enum() && _tag == #field_tag_name.as_ref() => {
-                        let __ele = <#vec_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
-                        #ident.push(__ele);
-                    }
-                    _tag if !(#type_for_is_enum_check::__is_enum()) && _tag == #field_tag_name.as_ref() => {
-                        let __ele = <#vec_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                let push = match &f.key_attr {
+                    | Some(key) => keyed_push(f, key, &field_tag_name, &ele_inherit_ns),
+                    | None => quote! {
+                        let __ele = <#vec_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #ele_inherit_ns
                         #ident.push(__ele);
+                    },
+                };
+                let push = limit_push(f, quote! {_tag}, push);
+                if let Some(wrapper) = &f.flatten_into {
+                    flatten_into_branch(wrapper, &field_tag_name, push)
+                } else {
+                    quote! {
+                        _tag if <#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum() && _tag == #field_tag_name.as_ref() => {
+                            #push
+                        }
+                        _tag if !(<#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum()) && _tag == #field_tag_name.as_ref() => {
+                            #push
+                        }
                     }
                 }
             },
             Generic::Opt(ref opt_ty) => {
                 quote! {
-                    _tag if #type_for_is_enum_check::__is_enum() && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#opt_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                    _tag if <#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum() && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#opt_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #ident = Some(__f);
                     }
-                    _tag if !(#type_for_is_enum_check::__is_enum()) && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#opt_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                    _tag if !(<#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum()) && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#opt_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #ident = Some(__f);
                     }
                 }
             },
             Generic::Boxed(ref inner_ty) => {
                  quote! {
-                    _tag if #type_for_is_enum_check::__is_enum() && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                    _tag if <#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum() && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #ident = Some(Box::new(__f));
                     }
-                    _tag if !(#type_for_is_enum_check::__is_enum()) && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                    _tag if !(<#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum()) && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #ident = Some(Box::new(__f));
                     }
                 }
             },
-            Generic::None => {
-                let assignment = if f.default.is_some() {
+            Generic::BoxedOpt(ref inner_ty) => {
+                quote! {
+                    _tag if <#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum() && _tag == #field_tag_name.as_ref() => {
+                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
+                        #ident = Box::new(Some(__f));
+                    }
+                    _tag if !(<#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum()) && _tag == #field_tag_name.as_ref() => {
+                        let __f = <#inner_ty as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
+                        #ident = Box::new(Some(__f));
+                    }
+                }
+            },
+            Generic::Res(..) | Generic::None => {
+                let tracked = !(f.default.is_some() || matches!(f.generic, Generic::Res(..)));
+                let mut assignment = if tracked || f.patching {
+                    quote! { #ident = Some(__f); }
+                } else {
                     quote! { #ident = __f; }
+                };
+                // The children counted by `at_most` and `at_least` may appear more than once
+                let check_dup = if tracked && f.at_most.is_none() && f.at_least.is_none() {
+                    check_dup
                 } else {
-                    quote! { #ident = Some(__f); }
+                    quote! {}
                 };
+                if f.at_most.is_some() || f.at_least.is_some() {
+                    let counter = cardinality_counter(f);
+                    assignment.extend(quote! { #counter += 1; });
+                }
                 quote! {
-                     _tag if #type_for_is_enum_check::__is_enum() && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#t as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                     _tag if <#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum() && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#t as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #assignment
                     }
-                    _tag if !(#type_for_is_enum_check::__is_enum()) && _tag == #field_tag_name.as_ref() => {
-                        let __f = <#t as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty);
+                    _tag if !(<#type_for_is_enum_check as ::xmlserde::XmlDeserialize>::__is_enum()) && _tag == #field_tag_name.as_ref() => {
+                        #check_dup
+                        let __f = <#t as ::xmlserde::XmlDeserialize>::deserialize(_tag, reader, s.attributes(), is_empty)?;
+                        #f_inherit_ns
                         #assignment
                     }
                 }
@@ -1026,7 +1839,7 @@ fn children_match_branch(
         }
         Ok(Event::Text(t)) => {
             use ::xmlserde::{XmlValue, XmlDeserialize};
-            let _str = t.unescape().expect("failed to unescape string");
+            let _str = t.unescape()?;
             if _str.trim() != "" {
                 #untag_text_enum
             }
@@ -1,4 +1,4 @@
-use crate::symbol::{MAP, OTHER, RENAME};
+use crate::symbol::{MAP, OTHER, RENAME, STRIP_PREFIX};
 use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Data, DeriveInput, Error, Fields, Ident, Type};
@@ -22,6 +22,28 @@ pub fn get_xml_serde_enum_impl_block(input: DeriveInput) -> Result<TokenStream,
         },
     };
 
+    // Parse #[xmlserde(strip_prefix = "ST_")] on the enum
+    let mut strip_prefix: Option<String> = None;
+    for attr in &input.attrs {
+        if attr.path().is_ident("xmlserde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path == STRIP_PREFIX {
+                    let value = meta.value()?;
+                    let lit_str: syn::LitStr = value.parse()?;
+                    strip_prefix = Some(lit_str.value());
+                } else if meta.input.peek(syn::Token![=]) {
+                    // Other attributes are for the other derives
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    let strip = |v: String| match &strip_prefix {
+        | Some(p) => v.strip_prefix(p.as_str()).map(str::to_string).unwrap_or(v),
+        | None => v,
+    };
+
     let mut parsed_variants = Vec::new();
 
     for variant in variants {
@@ -69,10 +91,10 @@ pub fn get_xml_serde_enum_impl_block(input: DeriveInput) -> Result<TokenStream,
 
         parsed_variants.push(EnumVariantInfo {
             ident: variant_ident,
-            xml_value: xml_value_str,
+            xml_value: strip(xml_value_str),
             is_other: is_other_attr,
             other_type: other_inner_type,
-            mapped_values,
+            mapped_values: mapped_values.into_iter().map(strip).collect(),
         });
     }
 
@@ -138,6 +160,11 @@ pub fn get_xml_serde_enum_impl_block(input: DeriveInput) -> Result<TokenStream,
         }
     };
 
+    let strip_input = match &strip_prefix {
+        | Some(p) => quote! {let s = s.strip_prefix(#p).unwrap_or(s);},
+        | None => quote! {},
+    };
+
     let ident = &input.ident;
     let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
 
@@ -150,6 +177,7 @@ pub fn get_xml_serde_enum_impl_block(input: DeriveInput) -> Result<TokenStream,
             }
 
             fn deserialize(s: &str) -> Result<Self, String> {
+                #strip_input
                 Ok(match s {
                     #deserialize_arms
                 })
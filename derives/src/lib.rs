@@ -17,15 +17,11 @@ pub fn derive_xml_deserialize(input: TokenStream) -> TokenStream {
     match Container::from_ast(&input, Derive::Deserialize) {
         | Ok(container) => {
             if let Err(e) = container.validate() {
-                return syn::Error::new_spanned(&input, e.to_string())
-                    .to_compile_error()
-                    .into();
+                return e.into_syn_error(&input).to_compile_error().into();
             }
             get_de_impl_block(input).into()
         },
-        | Err(e) => syn::Error::new_spanned(&input, e.to_string())
-            .to_compile_error()
-            .into(),
+        | Err(e) => e.into_syn_error(&input).to_compile_error().into(),
     }
 }
 
@@ -44,9 +40,7 @@ pub fn derive_xml_serialize(input: TokenStream) -> TokenStream {
     match Container::from_ast(&input, Derive::Serialize) {
         | Ok(container) => {
             if let Err(e) = container.validate() {
-                return syn::Error::new_spanned(&input, e.to_string())
-                    .to_compile_error()
-                    .into();
+                return e.into_syn_error(&input).to_compile_error().into();
             }
             let result = if container.is_enum() {
                 get_ser_enum_impl_block(container)
@@ -55,8 +49,6 @@ pub fn derive_xml_serialize(input: TokenStream) -> TokenStream {
             };
             result.into()
         },
-        | Err(e) => syn::Error::new_spanned(&input, e.to_string())
-            .to_compile_error()
-            .into(),
+        | Err(e) => e.into_syn_error(&input).to_compile_error().into(),
     }
 }
@@ -1,4 +1,4 @@
-use quote::quote;
+use quote::{format_ident, quote};
 
 use crate::container::{Container, EleType, FieldsSummary, Generic, StructField};
 
@@ -70,6 +70,16 @@ pub fn get_ser_enum_impl_block(container: Container) -> proc_macro2::TokenStream
             }
         }
     });
+    let get_roots = if !container.roots.is_empty() {
+        let roots = container.get_root_names();
+        quote! {
+            fn ser_roots() -> Vec<&'static [u8]> {
+                vec![#(#roots),*]
+            }
+        }
+    } else {
+        quote! {}
+    };
     quote! {
         #[allow(unused_must_use)]
         impl #impl_generics ::xmlserde::XmlSerialize for #ident #type_generics #where_clause {
@@ -83,6 +93,8 @@ pub fn get_ser_enum_impl_block(container: Container) -> proc_macro2::TokenStream
                     #(#branches)*
                 }
             }
+
+            #get_roots
         }
     }
 }
@@ -113,15 +125,18 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
         text,
         attrs,
         self_closed_children,
+        pis,
+        flattened,
+        attr_map,
         untagged_enums: untags,
         untagged_structs: _,
     } = FieldsSummary::from_fields(&container.struct_fields);
-    if text.is_some()
-        && (!children.is_empty() || !self_closed_children.is_empty() || !untags.is_empty())
-    {
-        panic!("Cannot have the text and children at the same time.")
-    }
-    let init = init_is_empty(&children, &self_closed_children, &untags, &text);
+    // A stable sort keeps the declaration order of the fields with the same `order`
+    let mut children = children;
+    children.sort_by_key(|f| f.order_key());
+    let mut attrs = attrs;
+    attrs.sort_by_key(|f| f.order_key());
+    let init = init_is_empty(&children, &self_closed_children, &pis, &untags, &text);
     let build_attr_and_push = attrs.iter().map(|attr| {
         let name = container
             .get_field_name(attr)
@@ -139,67 +154,113 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
                 panic!("No name or mapped_names or rename_all for field: {}", ident)
             });
         let ident = attr.original.ident.as_ref().unwrap();
+        let to_string = |v: proc_macro2::TokenStream| match &attr.serialize_with {
+            | Some(f) => quote! {#f(#v)},
+            | None => quote! {::xmlserde::XmlValue::serialize(#v)},
+        };
+        // The serialized value is declared outside of the conditions because `attrs` borrows it
+        let keep = attr
+            .skip_serializing_if
+            .as_ref()
+            .map(|p| quote! {!#p(&self.#ident)});
         match &attr.generic {
             | Generic::Vec(_) => panic!("cannot use a vector in attribute"),
             | Generic::Opt(_) => {
+                let sr = to_string(quote! {v});
+                let guard = keep.map(|k| quote! {if #k});
                 quote! {
                     let mut sr: String;
                     match &self.#ident {
-                        Some(v) => {
-                            sr = v.serialize();
+                        Some(v) #guard => {
+                            sr = #sr;
                             attrs.push(Attribute::from((#name.as_ref(), sr.as_bytes())));
                         },
-                        None => {},
+                        _ => {},
                     }
                 }
             },
-            | Generic::Boxed(_) => {
-                quote! { panic!("Attributes cannot be of type Box<T>"); }
-            },
-            | Generic::None => match &attr.default {
-                | Some(path) => {
-                    quote! {
-                        let mut ser;
-                        if #path() != self.#ident {
-                            ser = self.#ident.serialize();
+            | Generic::Boxed(_) | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
+                let ser = to_string(quote! {(&self.#ident)});
+                let cond = match (&attr.default, keep) {
+                    | (Some(path), Some(k)) => Some(quote! {#path() != self.#ident && #k}),
+                    | (Some(path), None) => Some(quote! {#path() != self.#ident}),
+                    | (None, k) => k,
+                };
+                match cond {
+                    | Some(cond) => {
+                        quote! {
+                            let mut ser;
+                            if #cond {
+                                ser = #ser;
+                                attrs.push(Attribute::from((#name.as_ref(), ser.as_bytes())));
+                            }
+                        }
+                    },
+                    | None => {
+                        quote! {
+                            let ser = #ser;
                             attrs.push(Attribute::from((#name.as_ref(), ser.as_bytes())));
                         }
-                    }
-                },
-                | None => {
-                    quote! {
-                        let ser = self.#ident.serialize();
-                        attrs.push(Attribute::from((#name.as_ref(), ser.as_bytes())));
-                    }
-                },
+                    },
+                }
             },
         }
     });
+    let push_flattened_attrs = flattened.iter().map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        let flat = format_ident!("__flat_{}", ident);
+        quote! {
+            let #flat = ::xmlserde::__flatten_attrs(&self.#ident);
+            for (k, v) in &#flat {
+                attrs.push(Attribute::from((k.as_slice(), v.as_slice())));
+            }
+        }
+    });
+    let push_attr_map = attr_map.map(|f| {
+        let ident = f.original.ident.as_ref().unwrap();
+        quote! {
+            for (k, v) in self.#ident.iter() {
+                attrs.push(Attribute::from((k.as_bytes(), v.as_bytes())));
+            }
+        }
+    });
     let write_text_or_children = if let Some(t) = text {
         let ident = t.original.ident.as_ref().unwrap();
-        match &t.generic {
+        let to_string = |v: proc_macro2::TokenStream| match &t.serialize_with {
+            | Some(f) => quote! {#f(#v)},
+            | None => quote! {::xmlserde::XmlValue::serialize(#v)},
+        };
+        let write_r = if matches!(t.ty, EleType::CData) {
+            quote! {::xmlserde::__write_cdata(writer, &r);}
+        } else {
+            quote! {
+                let event = BytesText::new(&r);
+                writer.write_event(Event::Text(event));
+            }
+        };
+        let write = match &t.generic {
             | Generic::Opt(opt_inner_ty) => {
                 let generic_of_opt_inner = crate::container::get_generics(opt_inner_ty);
                 if generic_of_opt_inner.is_boxed() {
+                    let to_boxed_string = to_string(quote! {&**__d});
                     quote! {
                         match &self.#ident {
                             None => {},
                             Some(__d) => { // __d is Box<DeepValue>
-                                let r = (*__d).serialize(); // XmlValue::serialize()
-                                let event = BytesText::new(&r);
-                                writer.write_event(Event::Text(event));
+                                let r = #to_boxed_string; // XmlValue::serialize()
+                                #write_r
                             }
                         }
                     }
                 } else {
                     // Option<Value>
+                    let to_string = to_string(quote! {__d});
                     quote! {
                         match &self.#ident {
                             None => {},
                             Some(__d) => { // __d is Value
-                                let r = __d.serialize(); // XmlValue::serialize()
-                                let event = BytesText::new(&r);
-                                writer.write_event(Event::Text(event));
+                                let r = #to_string; // XmlValue::serialize()
+                                #write_r
                             }
                         }
                     }
@@ -207,22 +268,23 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
             },
             | Generic::Boxed(_boxed_inner_ty) => {
                 // self.#ident is Box<Value>
+                let to_string = to_string(quote! {&*self.#ident});
                 quote! {
-                    let r = (*self.#ident).serialize(); // XmlValue::serialize()
-                    let event = BytesText::new(&r);
-                    writer.write_event(Event::Text(event));
+                    let r = #to_string; // XmlValue::serialize()
+                    #write_r
                 }
             },
-            | Generic::None => {
+            | Generic::BoxedOpt(_) | Generic::Res(..) | Generic::None => {
                 // self.#ident is Value
+                let to_string = to_string(quote! {&self.#ident});
                 quote! {
-                    let r = self.#ident.serialize(); // XmlValue::serialize()
-                    let event = BytesText::new(&r);
-                    writer.write_event(Event::Text(event));
+                    let r = #to_string; // XmlValue::serialize()
+                    #write_r
                 }
             },
             | Generic::Vec(_) => panic!("Vec cannot be text content"), // Should not happen
-        }
+        };
+        skip_if(&t, write)
     } else {
         let write_scf = self_closed_children.into_iter().map(|f| {
             let ident = f.original.ident.as_ref().unwrap();
@@ -266,13 +328,37 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
                         .unwrap_or_else(|| "<unnamed>".to_string());
                     panic!("No name or mapped_names or rename_all for field: {}", ident)
                 };
-                match &f.generic {
-                    | Generic::Boxed(_) => {
+                let write = match &f.generic {
+                    | _ if f.key_attr.is_some() => quote! {
+                        for __v in self.#ident.values() {
+                            __v.serialize(#name_ref.as_ref(), writer);
+                        }
+                    },
+                    | Generic::Boxed(_) | Generic::BoxedOpt(_) => {
                         quote! { (*self.#ident).serialize(#name_ref.as_ref(), writer); }
                     },
                     | _ => {
                         quote! { self.#ident.serialize(#name_ref.as_ref(), writer); }
                     },
+                };
+                let write = match &f.flatten_into {
+                    | Some(wrapper) => quote! {
+                        if !self.#ident.is_empty() {
+                            writer.write_event(Event::Start(BytesStart::new(String::from_utf8_lossy(#wrapper.as_ref()))));
+                            #write
+                            writer.write_event(Event::End(BytesEnd::new(String::from_utf8_lossy(#wrapper.as_ref()))));
+                        }
+                    },
+                    | None => write,
+                };
+                let write = skip_if(&f, write);
+                match &f.default {
+                    | Some(d) if f.skip_serializing_if_default => quote! {
+                        if self.#ident != #d() {
+                            #write
+                        }
+                    },
+                    | _ => write,
                 }
             }
         });
@@ -295,6 +381,25 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
             #(#write_untags)*
         }
     };
+    // The processing instructions come first in the element.
+    let write_pis = pis.iter().map(|f| {
+        let target = f.name.as_ref().expect("`pi` field should have a name");
+        let ident = f.original.ident.as_ref().unwrap();
+        quote! {
+            if let Some(__d) = &self.#ident {
+                let __c = format!(
+                    "{} {}",
+                    String::from_utf8_lossy(#target),
+                    ::xmlserde::XmlValue::serialize(__d),
+                );
+                writer.write_event(Event::PI(BytesPI::new(__c)));
+            }
+        }
+    });
+    let write_text_or_children = quote! {
+        #(#write_pis)*
+        #write_text_or_children
+    };
     let ident = &container.original.ident;
     let (impl_generics, type_generics, where_clause) = container.original.generics.split_for_impl();
     let write_event = quote! {
@@ -320,6 +425,22 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
     } else {
         quote! {}
     };
+    // A struct without fields and namespaces is always serialized into an empty root element.
+    let ser_static = match container.get_root_names().first() {
+        | Some(root)
+            if container.struct_fields.is_empty()
+                && container.with_ns.is_none()
+                && container.custom_ns.is_empty() =>
+        {
+            let xml = format!("<{}/>", String::from_utf8_lossy(&root.value()));
+            quote! {
+                fn __ser_static() -> Option<&'static str> {
+                    Some(#xml)
+                }
+            }
+        },
+        | _ => quote! {},
+    };
     quote! {
         #[allow(unused_must_use)]
         impl #impl_generics ::xmlserde::XmlSerialize for #ident #type_generics #where_clause {
@@ -337,24 +458,41 @@ pub fn get_ser_struct_impl_block(container: Container) -> proc_macro2::TokenStre
                 #write_ns
                 #write_custom_ns
                 #(#build_attr_and_push)*
+                #(#push_flattened_attrs)*
+                #push_attr_map
                 let start = start.with_attributes(attrs);
                 #init
                 #write_event
             }
             #get_roots
+            #ser_static
         }
     }
 }
 
+/// Wraps the writing of a field in its `skip_serializing_if` predicate if any.
+fn skip_if(f: &StructField, write: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let ident = f.original.ident.as_ref().unwrap();
+    match &f.skip_serializing_if {
+        | Some(p) => quote! {
+            if !#p(&self.#ident) {
+                #write
+            }
+        },
+        | None => write,
+    }
+}
+
 fn init_is_empty(
     children: &[StructField],
     scf: &[StructField],
+    pis: &[StructField],
     untags: &[StructField],
     text: &Option<StructField>,
 ) -> proc_macro2::TokenStream {
     let children_init = children.iter().map(|c| {
         let ident = c.original.ident.as_ref().unwrap();
-        match &c.generic {
+        let init = match &c.generic {
             | Generic::Vec(_) => {
                 quote! {
                     let #ident = self.#ident.len() > 0;
@@ -373,6 +511,16 @@ fn init_is_empty(
                 },
                 | None => quote! {let #ident = true;},
             },
+            | Generic::BoxedOpt(_) => {
+                quote! {
+                    let #ident = self.#ident.is_some();
+                }
+            },
+            | Generic::Res(..) => {
+                quote! {
+                    let #ident = self.#ident.is_ok();
+                }
+            },
             | Generic::None => match &c.default {
                 | Some(d) => {
                     quote! {
@@ -381,6 +529,13 @@ fn init_is_empty(
                 },
                 | None => quote! {let #ident = true;},
             },
+        };
+        match &c.skip_serializing_if {
+            | Some(p) => quote! {
+                #init
+                let #ident = #ident && !#p(&self.#ident);
+            },
+            | None => init,
         }
     });
     let has_untag_fields = !untags.is_empty();
@@ -393,7 +548,13 @@ fn init_is_empty(
     let text_init = match text {
         | Some(tf) => {
             let ident = tf.original.ident.as_ref().unwrap();
-            if tf.generic.is_opt() {
+            let skipped = match &tf.skip_serializing_if {
+                | Some(p) => quote! {
+                    let has_text = has_text && !#p(&self.#ident);
+                },
+                | None => quote! {},
+            };
+            let init = if tf.generic.is_opt() {
                 quote! {
                     let mut has_text = true;
                     if self.#ident.is_none() {
@@ -410,6 +571,10 @@ fn init_is_empty(
                         has_text = false;
                     }
                 }
+            };
+            quote! {
+                #init
+                #skipped
             }
         },
         | None => quote! {let has_text = false;},
@@ -419,8 +584,9 @@ fn init_is_empty(
             let ident = c.original.ident.as_ref().unwrap();
             quote! {#ident}
         });
+        let pis = pis.iter().map(|p| p.original.ident.as_ref().unwrap());
         quote! {
-            let has_child_to_write = #(#idents ||)* has_text;
+            let has_child_to_write = #(#idents ||)* #(self.#pis.is_some() ||)* has_text;
             let is_empty = !has_child_to_write && !#has_untag_fields;
         }
     };
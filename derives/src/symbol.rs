@@ -12,6 +12,8 @@ impl Symbol {
 }
 
 pub const DENY_UNKNOWN: Symbol = Symbol("deny_unknown_fields");
+pub const DENY_UNKNOWN_ATTRS: Symbol = Symbol("deny_unknown_attrs");
+pub const DENY_UNKNOWN_CHILDREN: Symbol = Symbol("deny_unknown_children");
 pub const WITH_NS: Symbol = Symbol("with_ns");
 pub const WITH_CUSTOM_NS: Symbol = Symbol("with_custom_ns");
 pub const ROOT: Symbol = Symbol("root");
@@ -19,15 +21,38 @@ pub const XML_SERDE: Symbol = Symbol("xmlserde");
 pub const NAME: Symbol = Symbol("name");
 pub const TYPE: Symbol = Symbol("ty");
 pub const SKIP_SERIALIZING: Symbol = Symbol("skip_serializing");
+pub const SKIP_SERIALIZING_IF_DEFAULT: Symbol = Symbol("skip_serializing_if_default");
+pub const SKIP_SERIALIZING_IF: Symbol = Symbol("skip_serializing_if");
+pub const SKIP_DESERIALIZING: Symbol = Symbol("skip_deserializing");
+pub const SKIP: Symbol = Symbol("skip");
 pub const VEC_SIZE: Symbol = Symbol("vec_size");
 pub const DEFAULT: Symbol = Symbol("default");
 pub const MAP: Symbol = Symbol("map");
+pub const TRIM: Symbol = Symbol("trim");
+pub const PRESERVE_NS: Symbol = Symbol("preserve_ns");
+pub const REQUIRED: Symbol = Symbol("required");
+pub const LIMIT: Symbol = Symbol("limit");
+pub const FLATTEN_INTO: Symbol = Symbol("flatten_into");
+pub const KEY_ATTR: Symbol = Symbol("key_attr");
+pub const ERROR_MODE: Symbol = Symbol("error_mode");
+pub const AT_MOST: Symbol = Symbol("at_most");
+pub const AT_LEAST: Symbol = Symbol("at_least");
+pub const SERIALIZE_WITH: Symbol = Symbol("serialize_with");
+pub const DESERIALIZE_WITH: Symbol = Symbol("deserialize_with");
+pub const XML_VALUE: Symbol = Symbol("xml_value");
+pub const ORDER: Symbol = Symbol("order");
+pub const STRIP_PREFIX: Symbol = Symbol("strip_prefix");
+pub const DERIVE_DEFAULT: Symbol = Symbol("derive_default");
 
 // Type values
 pub const TYPE_ATTR: Symbol = Symbol("attr");
+pub const TYPE_ATTR_MAP: Symbol = Symbol("attr_map");
 pub const TYPE_CHILD: Symbol = Symbol("child");
 pub const TYPE_TEXT: Symbol = Symbol("text");
+pub const TYPE_CDATA: Symbol = Symbol("cdata");
 pub const TYPE_SFC: Symbol = Symbol("sfc");
+pub const TYPE_PI: Symbol = Symbol("pi");
+pub const TYPE_FLATTEN: Symbol = Symbol("flatten");
 pub const TYPE_UNTAG: Symbol = Symbol("untag");
 pub const TYPE_UNTAGGED_ENUM: Symbol = Symbol("untagged_enum");
 pub const TYPE_UNTAGGED_STRUCT: Symbol = Symbol("untagged_struct");
@@ -91,7 +91,56 @@
 //! - default: assigning a parameter-free function to create a default value for a certain field.
 //!   Notice that it requires the type of this value impls `Eq` and it will skip serializing when
 //!   the value equals to the default one.
+//! - skip_serializing_if_default: a shorthand for `default` with the `Default` impl of the
+//!   field type. The field is skipped serializing when its value equals to `T::default()`.
+//! - skip_serializing_if: `skip_serializing_if = "String::is_empty"` on an `attr`, `child` or
+//!   `text` field leaves it out of the XML when the predicate, a `fn(&T) -> bool`, returns true.
+//! - skip_deserializing: the field is still serialized but never read in deserializing, like a
+//!   cache computed after loading. It gets the value of `default` or `Default::default()`.
+//! - skip: the field does not appear in the XML at all, so it takes neither `ty` nor `name`.
+//!   It is a shorthand for `skip_serializing` and `skip_deserializing`.
+//! - preserve_ns: on a `child` field of `Unparsed`, the namespace declarations of the parent
+//!   element are kept in the `Unparsed` so that it is still namespace-valid when serialized.
+//! - limit: `limit = 1000` on a `Vec<T>` field of `child`, `untag` or `untagged_enum` type stops
+//!   collecting the elements once there are 1000 of them. The rest are skipped, which prevents
+//!   running out of memory on the maliciously oversized documents.
+//! - flatten_into: `flatten_into = b"books"` on a `Vec<T>` field of `child` type reads and
+//!   writes the elements inside a `<books>` wrapper element, so that no dedicated wrapper struct
+//!   is needed.
+//! - key_attr: `key_attr = b"id"` on a `HashMap<K, V>` or `BTreeMap<K, V>` field of `child` type
+//!   reads the children into the map, keyed by their `id` attribute deserialized as `K`. A later
+//!   child with the same key replaces the earlier one. Only the values are written back, so `V`
+//!   should serialize the key attribute itself. A `HashMap` is written in an arbitrary order.
+//! - error_mode: on an `attr` field, decides what happens when the value fails to deserialize.
+//!   `"panic"` is the default and makes deserializing return an error, `"ignore"` keeps the field as if the attribute were missing and
+//!   `"default"` uses the `default` function or `Default::default()`.
+//! - at_most / at_least: on a `child` field that is neither `Vec<T>` nor `Option<T>`,
+//!   deserializing returns an error with the element name if the child occurs more than `at_most` or
+//!   fewer than `at_least` times.
+//! - serialize_with / deserialize_with: on an `attr` or `text` field, use the given functions
+//!   instead of the `XmlValue` impl, like `serialize_with = "to_hex"` and
+//!   `deserialize_with = "from_hex"`. They are `fn(&T) -> String` and
+//!   `fn(&str) -> Result<T, String>`, where `T` is the type inside the `Option<T>` if any.
+//!   Either of them can be used alone.
+//! - xml_value: `xml_value = "display_fromstr"` on an `attr` or `text` field uses the `Display`
+//!   and `FromStr` impls of the field type instead of `XmlValue`, like `XmlValueFromStr<T>`.
+//! - order: `order = 1` on an `attr` or `child` field decides its position in serializing,
+//!   when the schema requires another order than the declaration. The fields with the same order
+//!   keep their declaration order and the fields without `order` come last.
+//! - required: on an `Option<T>` field of `attr`, `child` or `text` type, deserializing returns an
+//!   error if the value is missing. It makes the schema intent visible while keeping the `Option<T>`.
 //! - untag: see the `Enum` above.
+//! - attr_map: `ty = "attr_map"` on a map like `HashMap<String, String>` collects the attributes
+//!   no other field takes, which are written back after the known ones. It cannot be used with
+//!   `deny_unknown_fields` or `deny_unknown_attrs`.
+//! - cdata: `ty = "cdata"` is a `text` field written as `<![CDATA[...]]>` instead of escaped
+//!   text. A `]]>` in the text is split into two sections. Both CDATA and escaped text are read.
+//! - flatten: `ty = "flatten"` on a field of a struct type inlines the attributes of that struct
+//!   into the element, without a wrapper element. It takes the attributes the other fields do
+//!   not, so the struct should neither declare a root nor have children. Only one level of
+//!   flattening is supported.
+//! - trim: `trim = true` on a `text` field strips the leading and trailing whitespaces of the
+//!   text before deserializing it.
 //!
 //! # Examples
 //! Please see [LogiSheets](https://github.com/proclml/LogiSheets/tree/master/crates/workbook) for examples.
@@ -114,6 +163,28 @@ pub trait XmlSerialize {
     fn ser_roots() -> Vec<&'static [u8]> {
         vec![]
     }
+
+    /// A helper function used by `xml_serialize_cow`.
+    ///
+    /// Returns the serialized root element if it is the same for every value of this type,
+    /// like a struct without fields.
+    fn __ser_static() -> Option<&'static str> {
+        None
+    }
+}
+
+impl<T: XmlSerialize> XmlSerialize for &T {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        (**self).serialize(tag, writer)
+    }
+
+    fn ser_roots() -> Vec<&'static [u8]> {
+        T::ser_roots()
+    }
+
+    fn __ser_static() -> Option<&'static str> {
+        T::__ser_static()
+    }
 }
 
 impl<T: XmlSerialize> XmlSerialize for Option<T> {
@@ -132,13 +203,235 @@ impl<T: XmlSerialize> XmlSerialize for Vec<T> {
     }
 }
 
+impl<T: XmlSerialize, const N: usize> XmlSerialize for [T; N] {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        self.iter().for_each(|c| {
+            c.serialize(tag, writer);
+        });
+    }
+}
+
+impl<T: XmlSerialize> XmlSerialize for std::collections::LinkedList<T> {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        self.iter().for_each(|c| {
+            c.serialize(tag, writer);
+        });
+    }
+}
+
+/// The entries are serialized as the children of `tag`, using the keys as their tags.
+impl<V: XmlSerialize> XmlSerialize for std::collections::BTreeMap<String, V> {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        serialize_map_entries(tag, self.iter(), writer)
+    }
+}
+
+/// The entries are serialized as the children of `tag`, using the keys as their tags.
+impl<V: XmlSerialize> XmlSerialize for std::collections::HashMap<String, V> {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        serialize_map_entries(tag, self.iter(), writer)
+    }
+}
+
+fn serialize_map_entries<'a, V, I, W>(tag: &[u8], entries: I, writer: &mut quick_xml::Writer<W>)
+where
+    V: XmlSerialize + 'a,
+    I: ExactSizeIterator<Item = (&'a String, &'a V)>,
+    W: Write,
+{
+    use quick_xml::events::*;
+    let start = BytesStart::new(String::from_utf8_lossy(tag));
+    if entries.len() == 0 {
+        let _ = writer.write_event(Event::Empty(start));
+        return;
+    }
+    let end = start.to_end().into_owned();
+    let _ = writer.write_event(Event::Start(start));
+    entries.for_each(|(k, v)| v.serialize(k.as_bytes(), writer));
+    let _ = writer.write_event(Event::End(end));
+}
+
+/// Writes `T1` with `tag` and `T2` with its first root right after it. `T2` should declare a
+/// root, or else it panics.
+impl<T1: XmlSerialize, T2: XmlSerialize> XmlSerialize for (T1, T2) {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        let roots = T2::ser_roots();
+        let second_tag = roots
+            .first()
+            .expect("the second type of a tuple should declare a root");
+        self.0.serialize(tag, writer);
+        self.1.serialize(second_tag, writer);
+    }
+}
+
+impl<T: XmlSerialize, E> XmlSerialize for Result<T, E> {
+    fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+        if let Ok(t) = self {
+            t.serialize(tag, writer)
+        }
+    }
+}
+
+/// The error of deserializing, which can be matched to tell a malformed document from a
+/// missing field or a value failing to parse.
+#[derive(Debug, Clone)]
+pub enum XmlError {
+    /// None of the `expected` root elements is found in the document.
+    MissingRoot { expected: Vec<String> },
+    /// The document is malformed or cannot be read.
+    ParseError(quick_xml::Error),
+    /// The required `field` of the `element` type is missing.
+    MissingRequiredField {
+        element: &'static str,
+        field: &'static str,
+    },
+    /// The `raw` value of `field` fails to deserialize.
+    DeserializeValueFailed { field: &'static str, raw: String },
+    /// An attribute or a child which is denied by `deny_unknown_fields`.
+    UnknownField(String),
+    /// The document ends before the element is closed.
+    Eof,
+    /// The document exceeds the `limit` of `XmlDeserializeOptions`, like `max_depth`.
+    LimitExceeded { limit: &'static str, max: usize },
+    /// An attribute or a single child appears more than once, with `strict_duplicate_fields`.
+    DuplicateField(String),
+    /// Any other error, like the ones from the custom `XmlDeserialize` impls.
+    Custom(String),
+}
+
+impl XmlError {
+    /// Creates a `Custom` error.
+    pub fn new(message: impl Into<String>) -> Self {
+        XmlError::Custom(message.into())
+    }
+}
+
+impl std::fmt::Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            | XmlError::MissingRoot { expected } => {
+                write!(f, "cannot find the root element: {}", expected.join(", "))
+            },
+            | XmlError::ParseError(e) => write!(f, "{}", e),
+            | XmlError::MissingRequiredField { element, field } => {
+                write!(f, "missing required field '{}' in '{}'", field, element)
+            },
+            | XmlError::DeserializeValueFailed { field, raw } => {
+                write!(f, "failed to deserialize '{}' from {:?}", field, raw)
+            },
+            | XmlError::UnknownField(name) => write!(f, "unknown field '{}'", name),
+            | XmlError::Eof => f.write_str("unexpected end of file"),
+            | XmlError::LimitExceeded { limit, max } => {
+                write!(f, "the document exceeds the {} of {}", limit, max)
+            },
+            | XmlError::DuplicateField(name) => write!(f, "duplicate field '{}'", name),
+            | XmlError::Custom(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for XmlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            | XmlError::ParseError(e) => Some(e),
+            | _ => None,
+        }
+    }
+}
+
+impl From<quick_xml::Error> for XmlError {
+    fn from(e: quick_xml::Error) -> Self {
+        XmlError::ParseError(e)
+    }
+}
+
+impl From<std::io::Error> for XmlError {
+    fn from(e: std::io::Error) -> Self {
+        XmlError::ParseError(quick_xml::Error::from(e))
+    }
+}
+
+/// An `XmlError` along with the byte offset in the document where it happens.
+///
+/// The offset can be turned into the line and the column by `line_column` with the original
+/// input.
+#[derive(Debug, Clone)]
+pub struct XmlErrorWithPos {
+    pub inner: XmlError,
+    pub byte_offset: u64,
+}
+
+impl XmlErrorWithPos {
+    pub fn new(inner: XmlError, byte_offset: u64) -> Self {
+        XmlErrorWithPos { inner, byte_offset }
+    }
+
+    /// The 1-based line and column of the error in `input`, which is the document that fails.
+    pub fn line_column(&self, input: &[u8]) -> (usize, usize) {
+        xml_line_column(input, self.byte_offset)
+    }
+}
+
+impl std::fmt::Display for XmlErrorWithPos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte offset {}", self.inner, self.byte_offset)
+    }
+}
+
+impl std::error::Error for XmlErrorWithPos {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.inner)
+    }
+}
+
+impl From<XmlErrorWithPos> for XmlError {
+    fn from(e: XmlErrorWithPos) -> Self {
+        e.inner
+    }
+}
+
+/// Converts the byte offset in `input` to the 1-based line and column. The column counts the
+/// characters rather than the bytes, and an offset beyond `input` points at its end.
+/// ```ignore
+/// let xml = "<a>\n  <b></a>";
+/// let err = xml_deserialize_from_str::<A>(xml).unwrap_err();
+/// let (line, column) = xml_line_column(xml.as_bytes(), err.byte_offset);
+/// ```
+pub fn xml_line_column(input: &[u8], byte_offset: u64) -> (usize, usize) {
+    let end = byte_offset.min(input.len() as u64) as usize;
+    let before = &input[..end];
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let column = String::from_utf8_lossy(&before[line_start..])
+        .chars()
+        .count()
+        + 1;
+    (line, column)
+}
+
+fn missing_root(roots: &[&[u8]]) -> XmlError {
+    XmlError::MissingRoot {
+        expected: roots
+            .iter()
+            .map(|r| String::from_utf8_lossy(r).into_owned())
+            .collect(),
+    }
+}
+
+fn no_root_declared() -> XmlError {
+    XmlError::new(r#"#[xmlserde(root = b"tag")]"#)
+}
+
 pub trait XmlDeserialize: Sized {
     fn deserialize<B: BufRead>(
         tag: &[u8],
         reader: &mut quick_xml::Reader<B>,
         attrs: quick_xml::events::attributes::Attributes,
         is_empty: bool,
-    ) -> Self;
+    ) -> Result<Self, XmlError>;
 
     fn de_roots() -> Vec<&'static [u8]> {
         vec![]
@@ -160,7 +453,9 @@ pub trait XmlDeserialize: Sized {
     /// know how to deal with an untag type. The current solution is to treat them as `Unparsed`
     /// types first, and then pass them into this function to deserialize. Since the type is
     /// untagged, it doesn't require the attributes.
-    fn __deserialize_from_unparsed_array(_array: Vec<(&'static [u8], Unparsed)>) -> Self {
+    fn __deserialize_from_unparsed_array(
+        _array: Vec<(&'static [u8], Unparsed)>,
+    ) -> Result<Self, XmlError> {
         unreachable!("untagged types require having `child` types only")
     }
 
@@ -173,11 +468,27 @@ pub trait XmlDeserialize: Sized {
         false
     }
 
-    fn __deserialize_from_text(_: &str) -> Option<Self>
+    fn __deserialize_from_text(_: &str) -> Result<Option<Self>, XmlError>
     where
         Self: Sized,
     {
-        None
+        Ok(None)
+    }
+
+    /// A helper function used by `xml_deserialize_into`.
+    ///
+    /// It deserializes the element into an existing value and only overwrites the fields
+    /// found in the XML. By default the whole value is replaced, and the derived structs
+    /// merge field by field.
+    fn __deserialize_into<B: BufRead>(
+        &mut self,
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<(), XmlError> {
+        *self = Self::deserialize(tag, reader, attrs, is_empty)?;
+        Ok(())
     }
 }
 
@@ -206,19 +517,13 @@ pub struct Unparsed {
 
 impl XmlSerialize for Unparsed {
     fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
-        use quick_xml::events::*;
-        let mut start = BytesStart::new(String::from_utf8_lossy(tag));
-        self.attrs.iter().for_each(|(k, v)| {
-            let k = k as &str;
-            let v = v as &str;
-            start.push_attribute((k, v));
-        });
+        let start = self.start_event(tag);
         if !self.data.is_empty() {
-            let _ = writer.write_event(Event::Start(start));
+            let _ = writer.write_event(Event::Start(start.borrow()));
             self.data.iter().for_each(|e| {
                 let _ = writer.write_event(e.clone());
             });
-            let _ = writer.write_event(Event::End(BytesEnd::new(String::from_utf8_lossy(tag))));
+            let _ = writer.write_event(Event::End(start.to_end()));
         } else {
             let _ = writer.write_event(Event::Empty(start));
         }
@@ -231,7 +536,7 @@ impl XmlDeserialize for Unparsed {
         reader: &mut quick_xml::Reader<B>,
         attrs: quick_xml::events::attributes::Attributes,
         is_empty: bool,
-    ) -> Self {
+    ) -> Result<Self, XmlError> {
         use quick_xml::events::*;
         let mut attrs_vec = Vec::<(String, String)>::new();
         let mut data = Vec::<Event<'static>>::new();
@@ -245,187 +550,1793 @@ impl XmlDeserialize for Unparsed {
             }
         });
         if is_empty {
-            return Unparsed {
+            return Ok(Unparsed {
                 data,
                 attrs: attrs_vec,
-            };
+            });
         }
         loop {
             match reader.read_event_into(&mut buf) {
                 | Ok(Event::End(e)) if e.name().into_inner() == tag => break,
-                | Ok(Event::Eof) => break,
-                | Err(_) => break,
+                | Ok(Event::Eof) => return Err(XmlError::Eof),
+                | Err(e) => return Err(e.into()),
                 | Ok(e) => data.push(e.into_owned()),
             }
         }
-        Unparsed {
+        Ok(Unparsed {
             data,
             attrs: attrs_vec,
-        }
+        })
     }
 
-    fn __deserialize_from_unparsed_array(_array: Vec<(&'static [u8], Unparsed)>) -> Self {
+    fn __deserialize_from_unparsed_array(
+        _array: Vec<(&'static [u8], Unparsed)>,
+    ) -> Result<Self, XmlError> {
         unreachable!(
             r#"seems you are using a struct having `attrs` or `text` as an UntaggedStruct"#
         )
     }
 }
 
-impl Unparsed {
-    pub fn deserialize_to<T>(self) -> Result<T, String>
-    where
-        T: XmlDeserialize + Sized,
-    {
-        // TODO: Find a more efficient way
-        let mut writer = quick_xml::Writer::new(Vec::new());
-        let t = b"tmptag";
-        self.serialize(t, &mut writer);
-        let result = writer.into_inner();
-
-        xml_deserialize_from_reader_with_root::<T, _>(result.as_slice(), t)
+/// Collects the children of `tag`, using their tags as the keys.
+impl<V: XmlDeserialize> XmlDeserialize for std::collections::BTreeMap<String, V> {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        _attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let mut result = Self::new();
+        read_map_entries(tag, reader, is_empty, |k, v| {
+            result.insert(k, v);
+        })?;
+        Ok(result)
     }
 }
 
-/// The entry for serializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
-/// to tell the serializer the tag name of the root. This function will add the header needed for
-/// a XML file.
-pub fn xml_serialize_with_decl<T>(obj: T) -> String
-where
-    T: XmlSerialize,
-{
-    use quick_xml::events::BytesDecl;
-    let mut writer = quick_xml::Writer::new(Vec::new());
-    let decl = BytesDecl::new("1.0", Some("UTF-8"), Some("yes"));
-    let _ = writer.write_event(Event::Decl(decl));
-    let roots = T::ser_roots();
-    if roots.is_empty() {
-        panic!(r#"Expect a root element to serialize: #[xmlserde(root=b"tag")]"#);
+/// Collects the children of `tag` as a property bag, using their tags as the keys and their
+/// texts as the values. `<properties><color>red</color></properties>` becomes
+/// `{"color": "red"}`.
+impl XmlDeserialize for std::collections::BTreeMap<String, String> {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        _attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let mut result = Self::new();
+        if is_empty {
+            return Ok(result);
+        }
+        let mut buf = Vec::<u8>::new();
+        // The key of the child being read and its text so far
+        let mut current: Option<(String, String)> = None;
+        let mut depth = 0usize;
+        loop {
+            match reader.read_event_into(&mut buf) {
+                | Ok(Event::End(e)) if depth == 0 && e.name().into_inner() == tag => break,
+                | Ok(Event::Start(s)) => {
+                    if depth == 0 {
+                        let key = String::from_utf8_lossy(s.name().into_inner()).into_owned();
+                        current = Some((key, String::new()));
+                    }
+                    depth += 1;
+                },
+                | Ok(Event::Empty(s)) if depth == 0 => {
+                    let key = String::from_utf8_lossy(s.name().into_inner()).into_owned();
+                    result.insert(key, String::new());
+                },
+                | Ok(Event::Text(t)) if depth == 1 => {
+                    let text = t.unescape()?;
+                    if let Some((_, v)) = current.as_mut() {
+                        v.push_str(&text);
+                    }
+                },
+                | Ok(Event::CData(t)) if depth == 1 => {
+                    if let Some((_, v)) = current.as_mut() {
+                        v.push_str(&String::from_utf8_lossy(&t));
+                    }
+                },
+                | Ok(Event::End(_)) => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        if let Some((k, v)) = current.take() {
+                            result.insert(k, v);
+                        }
+                    }
+                },
+                | Ok(Event::Eof) => return Err(XmlError::Eof),
+                | Err(e) => return Err(e.into()),
+                | _ => {},
+            }
+            buf.clear();
+        }
+        Ok(result)
     }
-    obj.serialize(roots[0], &mut writer);
-    String::from_utf8(writer.into_inner()).unwrap()
 }
 
-/// The entry for serializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
-/// to tell the serializer the tag name of the root.
-pub fn xml_serialize<T>(obj: T) -> String
-where
-    T: XmlSerialize,
-{
-    let mut writer = quick_xml::Writer::new(Vec::new());
-    let roots = T::ser_roots();
-    if roots.is_empty() {
-        panic!("Expect at least one root element");
+/// Collects the children of `tag`, using their tags as the keys.
+impl<V: XmlDeserialize> XmlDeserialize for std::collections::HashMap<String, V> {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        _attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let mut result = Self::new();
+        read_map_entries(tag, reader, is_empty, |k, v| {
+            result.insert(k, v);
+        })?;
+        Ok(result)
     }
-    obj.serialize(roots[0], &mut writer);
-    String::from_utf8(writer.into_inner()).expect("decode error")
 }
 
-/// The entry for deserializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
-/// to tell the deserializer which tag is the start for deserializing.
-/// ```ignore
-/// use xmlserde_derives::XmlDeserialize;
-/// #[derive(XmlDeserialize)]
-/// #[xmlserde(root=b"person")]
-/// pub struct Person {
-///     #[xmlserde(name = b"age", ty = "attr")]
-///     pub age: u16,
-///     #[xmlserde(name = b"pet", ty = "child")]
-///     pub pets: Vec<Pet>,
-/// }
-/// ```
-pub fn xml_deserialize_from_reader<T, R>(mut reader: R) -> Result<T, String>
-where
-    T: XmlDeserialize,
-    R: BufRead,
-{
-    let roots = T::de_roots();
-    if roots.is_empty() {
-        return Err(r#"#[xmlserde(root = b"tag")]"#.to_string());
-    }
-    // Read the entire input into a buffer
-    let mut buf = Vec::new();
-    reader.read_to_end(&mut buf).map_err(|e| e.to_string())?;
-    let mut last_err = None;
-    for root in &roots {
-        let mut cursor = std::io::Cursor::new(&buf);
-        match xml_deserialize_from_reader_with_root(&mut cursor, root) {
-            | Ok(val) => return Ok(val),
-            | Err(e) => last_err = Some(e),
-        }
+/// Groups the children of `tag` by their tags. The repeated tags are appended to the same entry.
+impl<T: XmlDeserialize> XmlDeserialize for std::collections::HashMap<String, Vec<T>> {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        _attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let mut result = Self::new();
+        read_map_entries(tag, reader, is_empty, |k, v| {
+            result.entry(k).or_insert_with(Vec::new).push(v);
+        })?;
+        Ok(result)
     }
-    Err(last_err.unwrap_or_else(|| "No matching root found".to_string()))
 }
 
-pub(crate) fn xml_deserialize_from_reader_with_root<T, R>(
-    reader: R,
-    root: &[u8],
-) -> Result<T, String>
+/// Deserializes every child of `tag` into `V` and hands it over to `f` along with its tag.
+fn read_map_entries<B, V, F>(
+    tag: &[u8],
+    reader: &mut quick_xml::Reader<B>,
+    is_empty: bool,
+    mut f: F,
+) -> Result<(), XmlError>
 where
-    T: XmlDeserialize,
-    R: BufRead,
+    B: BufRead,
+    V: XmlDeserialize,
+    F: FnMut(String, V),
 {
-    let mut reader = quick_xml::Reader::from_reader(reader);
+    if is_empty {
+        return Ok(());
+    }
     let mut buf = Vec::<u8>::new();
-    let rename_all = T::rename_all();
-    let transformed_root = rename_all.transform(root);
-
     loop {
-        match reader.read_event_into(&mut buf) {
-            | Ok(Event::Start(start)) => {
-                let name = start.name().into_inner();
-                let transformed_name = rename_all.transform(name);
-                if transformed_name == transformed_root {
-                    let result = T::deserialize(root, &mut reader, start.attributes(), false);
-                    return Ok(result);
-                }
-            },
-            | Ok(Event::Empty(start)) => {
-                let name = start.name().into_inner();
-                let transformed_name = rename_all.transform(name);
-                if transformed_name == transformed_root {
-                    let result = T::deserialize(root, &mut reader, start.attributes(), true);
-                    return Ok(result);
-                }
-            },
-            | Ok(Event::Eof) => {
-                return Err(format!(
-                    "Cannot find the element: {}",
-                    String::from_utf8_lossy(root)
-                ))
-            },
-            | Err(e) => return Err(e.to_string()),
-            | _ => {},
-        }
+        let (s, is_empty) = match reader.read_event_into(&mut buf) {
+            | Ok(Event::End(e)) if e.name().into_inner() == tag => return Ok(()),
+            | Ok(Event::Start(s)) => (s, false),
+            | Ok(Event::Empty(s)) => (s, true),
+            | Ok(Event::Eof) => return Err(XmlError::Eof),
+            | Err(e) => return Err(e.into()),
+            | _ => continue,
+        };
+        let key = s.name().into_inner();
+        let value = V::deserialize(key, reader, s.attributes(), is_empty)?;
+        f(String::from_utf8_lossy(key).into_owned(), value);
     }
 }
 
-/// The entry for deserializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
-/// to tell the deserializer which tag is the start for deserializing.
-/// ```ignore
-/// use xmlserde_derives::XmlDeserialize;
-/// #[derive(XmlDeserialize)]
-/// #[xmlserde(root=b"person")]
-/// pub struct Person {
-///     #[xmlserde(name = b"age", ty = "attr")]
-///     pub age: u16,
-///     #[xmlserde(name = b"pet", ty = "child")]
-///     pub pets: Vec<Pet>,
-/// }
-/// ```
-pub fn xml_deserialize_from_str<T>(xml_str: &str) -> Result<T, String>
-where
-    T: XmlDeserialize,
-{
-    xml_deserialize_from_reader(xml_str.as_bytes())
-}
-
-pub trait XmlValue: Sized {
-    fn serialize(&self) -> String;
-    fn deserialize(s: &str) -> Result<Self, String>;
+/// A `Result<T, E>` child keeps the parsing going when `T` fails to deserialize: the element is
+/// buffered first so that the rest of it is skipped whatever `T` stops at, and the error is
+/// turned into `Err(E::default())`. A missing child is `Err(E::default())` as well.
+impl<T: XmlDeserialize, E: Default> XmlDeserialize for Result<T, E> {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let unparsed = Unparsed::deserialize(tag, reader, attrs, is_empty)?;
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        unparsed.serialize(tag, &mut writer);
+        let buffered = writer.into_inner();
+        let result = xml_deserialize_from_reader_with_root::<T, _>(buffered.as_slice(), tag);
+        Ok(result.map_err(|_| E::default()))
+    }
 }
 
-impl XmlValue for bool {
-    fn serialize(&self) -> String {
+/// A `(T1, T2)` child is read from two consecutive elements: `tag` for `T1` and the next
+/// element for `T2`, whose tag should be one of its roots. Like in serializing, `T2` should
+/// declare a root, or else it is an error.
+impl<T1: XmlDeserialize, T2: XmlDeserialize> XmlDeserialize for (T1, T2) {
+    fn deserialize<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        let first = T1::deserialize(tag, reader, attrs, is_empty)?;
+        let mut buf = Vec::<u8>::new();
+        let (start, is_empty) = loop {
+            match reader.read_event_into(&mut buf) {
+                | Ok(Event::Start(s)) => break (s, false),
+                | Ok(Event::Empty(s)) => break (s, true),
+                | Ok(Event::End(_)) | Ok(Event::Eof) => {
+                    return Err(XmlError::new(format!(
+                        "expected an element after '{}'",
+                        String::from_utf8_lossy(tag)
+                    )))
+                },
+                | Err(e) => return Err(e.into()),
+                | _ => {},
+            }
+        };
+        let second_tag = start.name().into_inner();
+        let roots = T2::de_roots();
+        if roots.is_empty() {
+            return Err(XmlError::new(
+                "the second type of a tuple should declare a root",
+            ));
+        }
+        let rename_all = T2::rename_all();
+        let transformed = rename_all.transform(second_tag);
+        if !roots.iter().any(|r| rename_all.transform(r) == transformed) {
+            return Err(XmlError::new(format!(
+                "expected one of {:?} after '{}', got '{}'",
+                roots
+                    .iter()
+                    .map(|r| String::from_utf8_lossy(r))
+                    .collect::<Vec<_>>(),
+                String::from_utf8_lossy(tag),
+                String::from_utf8_lossy(second_tag)
+            )));
+        }
+        let second = T2::deserialize(second_tag, reader, start.attributes(), is_empty)?;
+        Ok((first, second))
+    }
+
+    fn __get_children_tags() -> Vec<&'static [u8]> {
+        T1::de_roots()
+    }
+}
+
+impl Unparsed {
+    /// Reads the element `tag` from `reader`, like `XmlDeserialize::deserialize`. It helps the
+    /// custom `XmlDeserialize` impls which keep some parts of the document unparsed.
+    pub fn from_reader<B: BufRead>(
+        tag: &[u8],
+        reader: &mut quick_xml::Reader<B>,
+        attrs: quick_xml::events::attributes::Attributes,
+        is_empty: bool,
+    ) -> Result<Self, XmlError> {
+        <Unparsed as XmlDeserialize>::deserialize(tag, reader, attrs, is_empty)
+    }
+
+    /// Declares the namespaces inherited from the ancestors on this element, except those
+    /// already declared by itself. It keeps this element namespace-valid when it is serialized
+    /// on its own. It is used by the `preserve_ns` fields.
+    pub fn inherit_ns(&mut self, decls: &[(String, String)]) {
+        let inherited = decls
+            .iter()
+            .filter(|(k, _)| !self.attrs.iter().any(|(key, _)| key == k))
+            .cloned()
+            .collect::<Vec<_>>();
+        self.attrs.splice(0..0, inherited);
+    }
+
+    /// The number of the attributes of this element.
+    pub fn attr_count(&self) -> usize {
+        self.attrs.len()
+    }
+
+    /// The number of the direct child elements, without deserializing them.
+    pub fn child_count(&self) -> usize {
+        let mut depth = 0usize;
+        let mut count = 0;
+        self.data.iter().for_each(|e| match e {
+            | Event::Start(_) => {
+                if depth == 0 {
+                    count += 1;
+                }
+                depth += 1;
+            },
+            | Event::Empty(_) if depth == 0 => count += 1,
+            | Event::End(_) => depth = depth.saturating_sub(1),
+            | _ => {},
+        });
+        count
+    }
+
+    /// Replaces the value of the attribute `name` with `f(value)`. Returns `false` if this
+    /// element has no such attribute.
+    ///
+    /// ```ignore
+    /// unparsed.patch_attr("href", |url| url.replace("http://", "https://"));
+    /// ```
+    pub fn patch_attr(&mut self, name: &str, f: impl FnOnce(&str) -> String) -> bool {
+        match self.attrs.iter_mut().find(|(k, _)| k == name) {
+            | Some((_, v)) => {
+                *v = f(v);
+                true
+            },
+            | None => false,
+        }
+    }
+
+    /// Consumes the `Unparsed` and returns the raw `quick_xml` events of the element named
+    /// `tag`: a `Start` event carrying the attributes, the inner events and the `End` event, or a
+    /// single `Empty` event if there is no content. They can be written to another
+    /// `quick_xml::Writer` after some custom event-level processing.
+    pub fn into_events(self, tag: &[u8]) -> Vec<Event<'static>> {
+        let start = self.start_event(tag).into_owned();
+        if self.data.is_empty() {
+            return vec![Event::Empty(start)];
+        }
+        let end = start.to_end().into_owned();
+        let mut events = Vec::with_capacity(self.data.len() + 2);
+        events.push(Event::Start(start));
+        events.extend(self.data);
+        events.push(Event::End(end));
+        events
+    }
+
+    fn start_event(&self, tag: &[u8]) -> quick_xml::events::BytesStart<'static> {
+        let mut start =
+            quick_xml::events::BytesStart::new(String::from_utf8_lossy(tag).into_owned());
+        self.attrs.iter().for_each(|(k, v)| {
+            start.push_attribute((k.as_str(), v.as_str()));
+        });
+        start
+    }
+
+    pub fn deserialize_to<T>(self) -> Result<T, XmlError>
+    where
+        T: XmlDeserialize + Sized,
+    {
+        // TODO: Find a more efficient way
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        let t = b"tmptag";
+        self.serialize(t, &mut writer);
+        let result = writer.into_inner();
+
+        // The position in the buffer above means nothing to the caller
+        xml_deserialize_from_reader_with_root::<T, _>(result.as_slice(), t).map_err(XmlError::from)
+    }
+}
+
+/// Parses the first element of the XML. Its tag is dropped, as `Unparsed` is serialized with the
+/// tag given by the caller.
+impl std::convert::TryFrom<&str> for Unparsed {
+    type Error = XmlError;
+
+    fn try_from(xml: &str) -> Result<Self, Self::Error> {
+        read_root_unparsed(xml).map(|(_, unparsed)| unparsed)
+    }
+}
+
+impl std::convert::TryFrom<String> for Unparsed {
+    type Error = XmlError;
+
+    fn try_from(xml: String) -> Result<Self, Self::Error> {
+        Self::try_from(xml.as_str())
+    }
+}
+
+/// `XmlBuilder` constructs an XML element programmatically, which is an imperative alternative
+/// to deriving `XmlSerialize` for the one-off XML generation.
+///
+/// ```ignore
+/// use xmlserde::XmlBuilder;
+///
+/// let xml = XmlBuilder::element("person")
+///     .attr("age", "8")
+///     .child("pet", |pet| {
+///         pet.attr("t", "cat").text("Tom");
+///     })
+///     .build();
+/// assert_eq!(xml, r#"<person age="8"><pet t="cat">Tom</pet></person>"#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct XmlBuilder {
+    tag: String,
+    attrs: Vec<(String, String)>,
+    contents: Vec<XmlBuilderContent>,
+}
+
+#[derive(Debug, Clone)]
+enum XmlBuilderContent {
+    Text(String),
+    Child(XmlBuilder),
+}
+
+impl XmlBuilder {
+    pub fn element(tag: &str) -> Self {
+        XmlBuilder {
+            tag: tag.to_string(),
+            attrs: vec![],
+            contents: vec![],
+        }
+    }
+
+    pub fn attr(&mut self, name: &str, value: &str) -> &mut Self {
+        self.attrs.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn text(&mut self, content: &str) -> &mut Self {
+        self.contents
+            .push(XmlBuilderContent::Text(content.to_string()));
+        self
+    }
+
+    /// Appends a child element named `tag`, which is built in the closure `f`.
+    pub fn child(&mut self, tag: &str, f: impl FnOnce(&mut XmlBuilder)) -> &mut Self {
+        let mut child = XmlBuilder::element(tag);
+        f(&mut child);
+        self.contents.push(XmlBuilderContent::Child(child));
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let mut writer = quick_xml::Writer::new(Vec::new());
+        self.write(&mut writer);
+        String::from_utf8(writer.into_inner()).expect("decode error")
+    }
+
+    fn write<W: Write>(&self, writer: &mut quick_xml::Writer<W>) {
+        use quick_xml::events::*;
+        let mut start = BytesStart::new(self.tag.as_str());
+        self.attrs.iter().for_each(|(k, v)| {
+            start.push_attribute((k.as_str(), v.as_str()));
+        });
+        if self.contents.is_empty() {
+            let _ = writer.write_event(Event::Empty(start));
+            return;
+        }
+        let _ = writer.write_event(Event::Start(start));
+        self.contents.iter().for_each(|c| match c {
+            | XmlBuilderContent::Text(t) => {
+                let _ = writer.write_event(Event::Text(BytesText::new(t)));
+            },
+            | XmlBuilderContent::Child(child) => child.write(writer),
+        });
+        let _ = writer.write_event(Event::End(BytesEnd::new(self.tag.as_str())));
+    }
+}
+
+/// The entry for serializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
+/// to tell the serializer the tag name of the root. This function will add the header needed for
+/// a XML file.
+pub fn xml_serialize_with_decl<T>(obj: T) -> String
+where
+    T: XmlSerialize,
+{
+    serialize_with_prolog(obj, quick_xml::Writer::new(Vec::new()), None)
+}
+
+/// Serializes like `xml_serialize_with_decl` and puts `<!DOCTYPE {doctype}>` between the XML
+/// declaration and the root element. `doctype` is written verbatim, e.g. `html` or
+/// `svg PUBLIC "-//W3C//DTD SVG 1.1//EN" "http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd"`.
+pub fn xml_serialize_with_doctype<T>(obj: T, doctype: &str) -> String
+where
+    T: XmlSerialize,
+{
+    use quick_xml::events::BytesText;
+    let doctype = Event::DocType(BytesText::from_escaped(doctype));
+    serialize_with_prolog(obj, quick_xml::Writer::new(Vec::new()), Some(doctype))
+}
+
+/// Serializes like `xml_serialize_with_decl` and puts `<!-- {header_comment} -->` between the
+/// XML declaration and the root element, e.g. for a "do not edit" warning. A `--`, which a
+/// comment cannot contain, is written as `- -`.
+pub fn xml_serialize_with_header<T>(obj: T, header_comment: &str) -> String
+where
+    T: XmlSerialize,
+{
+    use quick_xml::events::BytesText;
+    let mut comment = header_comment.to_string();
+    while comment.contains("--") {
+        comment = comment.replace("--", "- -");
+    }
+    let comment = Event::Comment(BytesText::from_escaped(format!(" {} ", comment)));
+    serialize_with_prolog(obj, quick_xml::Writer::new(Vec::new()), Some(comment))
+}
+
+/// Writes the XML declaration, the `prolog` event if any and then the root element.
+fn serialize_with_prolog<T>(
+    obj: T,
+    mut writer: quick_xml::Writer<Vec<u8>>,
+    prolog: Option<Event>,
+) -> String
+where
+    T: XmlSerialize,
+{
+    use quick_xml::events::BytesDecl;
+    let decl = BytesDecl::new("1.0", Some("UTF-8"), Some("yes"));
+    let _ = writer.write_event(Event::Decl(decl));
+    if let Some(event) = prolog {
+        let _ = writer.write_event(event);
+    }
+    let roots = T::ser_roots();
+    if roots.is_empty() {
+        panic!(r#"Expect a root element to serialize: #[xmlserde(root=b"tag")]"#);
+    }
+    obj.serialize(roots[0], &mut writer);
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+/// The entry for serializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
+/// to tell the serializer the tag name of the root.
+pub fn xml_serialize<T>(obj: T) -> String
+where
+    T: XmlSerialize,
+{
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let roots = T::ser_roots();
+    if roots.is_empty() {
+        panic!("Expect at least one root element");
+    }
+    obj.serialize(roots[0], &mut writer);
+    String::from_utf8(writer.into_inner()).expect("decode error")
+}
+
+/// Serializes like `xml_serialize` but puts each element on its own line, indented by 2 spaces
+/// per level.
+pub fn xml_serialize_pretty<T>(obj: T) -> String
+where
+    T: XmlSerialize,
+{
+    xml_serialize_pretty_indent(obj, b' ', 2)
+}
+
+/// Serializes like `xml_serialize_pretty` with the XML declaration before the root element.
+pub fn xml_serialize_pretty_with_decl<T>(obj: T) -> String
+where
+    T: XmlSerialize,
+{
+    let writer = quick_xml::Writer::new_with_indent(Vec::new(), b' ', 2);
+    serialize_with_prolog(obj, writer, None)
+}
+
+/// Serializes like `xml_serialize_pretty` but indents each level by `size` times `char`, like
+/// `b'\t'` and 1.
+pub fn xml_serialize_pretty_indent<T>(obj: T, char: u8, size: usize) -> String
+where
+    T: XmlSerialize,
+{
+    let mut writer = quick_xml::Writer::new_with_indent(Vec::new(), char, size);
+    let roots = T::ser_roots();
+    if roots.is_empty() {
+        panic!("Expect at least one root element");
+    }
+    obj.serialize(roots[0], &mut writer);
+    String::from_utf8(writer.into_inner()).expect("decode error")
+}
+
+/// Serializes like `xml_serialize` but streams the XML into `writer` instead of building a
+/// `String`, so that a large document is never held in memory.
+pub fn xml_serialize_to_writer<W, T>(obj: T, writer: W) -> std::io::Result<()>
+where
+    W: Write,
+    T: XmlSerialize,
+{
+    serialize_to_writer(
+        obj,
+        quick_xml::Writer::new(ErrorCapture::new(writer)),
+        false,
+    )
+}
+
+/// Streams like `xml_serialize_to_writer` with the XML declaration before the root element.
+pub fn xml_serialize_with_decl_to_writer<W, T>(obj: T, writer: W) -> std::io::Result<()>
+where
+    W: Write,
+    T: XmlSerialize,
+{
+    serialize_to_writer(obj, quick_xml::Writer::new(ErrorCapture::new(writer)), true)
+}
+
+/// Streams like `xml_serialize_to_writer` but indents each level by `size` times `char`, like
+/// `xml_serialize_pretty_indent`.
+pub fn xml_serialize_pretty_to_writer<W, T>(
+    obj: T,
+    writer: W,
+    char: u8,
+    size: usize,
+) -> std::io::Result<()>
+where
+    W: Write,
+    T: XmlSerialize,
+{
+    let writer = quick_xml::Writer::new_with_indent(ErrorCapture::new(writer), char, size);
+    serialize_to_writer(obj, writer, false)
+}
+
+fn serialize_to_writer<W, T>(
+    obj: T,
+    mut writer: quick_xml::Writer<ErrorCapture<W>>,
+    with_decl: bool,
+) -> std::io::Result<()>
+where
+    W: Write,
+    T: XmlSerialize,
+{
+    let roots = T::ser_roots();
+    if roots.is_empty() {
+        panic!("Expect at least one root element");
+    }
+    if with_decl {
+        let decl = quick_xml::events::BytesDecl::new("1.0", Some("UTF-8"), Some("yes"));
+        let _ = writer.write_event(Event::Decl(decl));
+    }
+    obj.serialize(roots[0], &mut writer);
+    let mut inner = writer.into_inner();
+    match inner.error.take() {
+        | Some(e) => Err(e),
+        | None => inner.inner.flush(),
+    }
+}
+
+/// Serializes like `xml_serialize_with_decl` into the file at `path`, which is created or
+/// truncated. The XML is streamed through a `BufWriter`.
+pub fn xml_serialize_to_file<T, P>(obj: T, path: P) -> std::io::Result<()>
+where
+    T: XmlSerialize,
+    P: AsRef<std::path::Path>,
+{
+    let file = std::fs::File::create(path)?;
+    xml_serialize_with_decl_to_writer(obj, std::io::BufWriter::new(file))
+}
+
+/// Keeps the first error of `inner`, which the derived `serialize` does not return.
+struct ErrorCapture<W: Write> {
+    inner: W,
+    error: Option<std::io::Error>,
+}
+
+impl<W: Write> ErrorCapture<W> {
+    fn new(inner: W) -> Self {
+        ErrorCapture { inner, error: None }
+    }
+}
+
+impl<W: Write> Write for ErrorCapture<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.error.is_some() {
+            // Nothing is written after the first error, so the output is not corrupted
+            return Ok(buf.len());
+        }
+        match self.inner.write(buf) {
+            | Err(e) if e.kind() != std::io::ErrorKind::Interrupted => {
+                self.error = Some(e);
+                Ok(buf.len())
+            },
+            | r => r,
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Serializes like `xml_serialize` but borrows the output without allocating when it is the
+/// same for every value of `T`, like a struct without fields.
+pub fn xml_serialize_cow<T>(obj: T) -> std::borrow::Cow<'static, str>
+where
+    T: XmlSerialize,
+{
+    match T::__ser_static() {
+        | Some(s) => std::borrow::Cow::Borrowed(s),
+        | None => std::borrow::Cow::Owned(xml_serialize(obj)),
+    }
+}
+
+/// Serializes each item with the root tag of `T` and concatenates them without a common
+/// container element, e.g. for the batch files or the include fragments.
+pub fn xml_serialize_all<T>(items: impl Iterator<Item = T>) -> String
+where
+    T: XmlSerialize,
+{
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    let roots = T::ser_roots();
+    if roots.is_empty() {
+        panic!("Expect at least one root element");
+    }
+    items.for_each(|item| item.serialize(roots[0], &mut writer));
+    String::from_utf8(writer.into_inner()).expect("decode error")
+}
+
+/// Serializes `value` with `tag` as its root, deserializes it back and tells whether the result
+/// equals `value`. It helps testing the types without a declared `root`.
+pub fn xml_fragment_roundtrip<T>(value: T, tag: &[u8]) -> bool
+where
+    T: XmlSerialize + XmlDeserialize + PartialEq,
+{
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    value.serialize(tag, &mut writer);
+    let xml = writer.into_inner();
+    match xml_deserialize_from_reader_with_root::<T, _>(xml.as_slice(), tag) {
+        | Ok(result) => result == value,
+        | Err(_) => false,
+    }
+}
+
+/// The entry for deserializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
+/// to tell the deserializer which tag is the start for deserializing.
+/// ```ignore
+/// use xmlserde_derives::XmlDeserialize;
+/// #[derive(XmlDeserialize)]
+/// #[xmlserde(root=b"person")]
+/// pub struct Person {
+///     #[xmlserde(name = b"age", ty = "attr")]
+///     pub age: u16,
+///     #[xmlserde(name = b"pet", ty = "child")]
+///     pub pets: Vec<Pet>,
+/// }
+/// ```
+pub fn xml_deserialize_from_reader<T, R>(reader: R) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+    R: BufRead,
+{
+    xml_deserialize_from_reader_with_options(reader, &XmlDeserializeOptions::default())
+}
+
+/// Like `xml_deserialize_from_reader`, but with the given options.
+pub fn xml_deserialize_from_reader_with_options<T, R>(
+    mut reader: R,
+    opts: &XmlDeserializeOptions,
+) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+    R: BufRead,
+{
+    let roots = T::de_roots();
+    if roots.is_empty() {
+        return Err(XmlErrorWithPos::new(no_root_declared(), 0));
+    }
+    // Read the entire input into a buffer
+    let mut buf = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut buf) {
+        return Err(XmlErrorWithPos::new(e.into(), buf.len() as u64));
+    }
+    deserialize_from_roots(&roots, &buf, opts)
+}
+
+/// Deserializes the element of the first of the `roots` found in `buf`. The earlier roots take
+/// precedence over the later ones regardless of where they are in the document.
+fn deserialize_from_roots<T>(
+    roots: &[&[u8]],
+    buf: &[u8],
+    opts: &XmlDeserializeOptions,
+) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    if opts.has_checks() {
+        check_limits(buf, opts)?;
+    }
+    let _guard = OptionsGuard::new(opts);
+    let (idx, offset) = find_first_root(buf, roots, T::rename_all(), &opts.reader)?;
+    read_from_root(
+        &buf[offset..],
+        roots[idx],
+        T::rename_all(),
+        &opts.reader,
+        |tag, reader, attrs, b| T::deserialize(tag, reader, attrs, b),
+    )
+    .map_err(|e| XmlErrorWithPos::new(e.inner, e.byte_offset + offset as u64))
+}
+
+/// Scans `buf` once and returns the index of the first of the `roots` found in it, along with
+/// the offset to start reading its element from.
+fn find_first_root(
+    buf: &[u8],
+    roots: &[&[u8]],
+    rename_all: Case,
+    config: &ReaderConfig,
+) -> Result<(usize, usize), XmlErrorWithPos> {
+    let transformed_roots = roots
+        .iter()
+        .map(|r| rename_all.transform(r))
+        .collect::<Vec<_>>();
+    let mut reader = quick_xml::Reader::from_reader(buf);
+    config.apply(reader.config_mut());
+    let mut event_buf = Vec::<u8>::new();
+    let mut found = Option::<(usize, usize)>::None;
+    loop {
+        event_buf.clear();
+        let offset = reader.buffer_position() as usize;
+        let name = match reader.read_event_into(&mut event_buf) {
+            | Ok(Event::Start(s)) | Ok(Event::Empty(s)) => {
+                rename_all.transform(s.name().into_inner())
+            },
+            | Ok(Event::Eof) => break,
+            // The element found so far is read as it was before the error
+            | Err(_) if found.is_some() => break,
+            | Err(e) => return Err(XmlErrorWithPos::new(e.into(), reader.error_position())),
+            | _ => continue,
+        };
+        let idx = transformed_roots.iter().position(|r| *r == name);
+        match (idx, found) {
+            | (Some(0), _) => return Ok((0, offset)),
+            | (Some(i), Some((j, _))) if i >= j => {},
+            | (Some(i), _) => found = Some((i, offset)),
+            | (None, _) => {},
+        }
+    }
+    found.ok_or_else(|| XmlErrorWithPos::new(missing_root(roots), reader.buffer_position()))
+}
+
+pub(crate) fn xml_deserialize_from_reader_with_root<T, R>(
+    reader: R,
+    root: &[u8],
+) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+    R: BufRead,
+{
+    let config = ReaderConfig::default();
+    read_from_root(
+        reader,
+        root,
+        T::rename_all(),
+        &config,
+        |tag, reader, attrs, is_empty| T::deserialize(tag, reader, attrs, is_empty),
+    )
+}
+
+/// Looks for the `root` element and hands it over to `f`. The errors of `f` are located at where
+/// the reader stops, as the derived code returns as soon as it fails.
+fn read_from_root<R, F, O>(
+    reader: R,
+    root: &[u8],
+    rename_all: Case,
+    config: &ReaderConfig,
+    f: F,
+) -> Result<O, XmlErrorWithPos>
+where
+    R: BufRead,
+    F: FnOnce(
+        &[u8],
+        &mut quick_xml::Reader<R>,
+        quick_xml::events::attributes::Attributes,
+        bool,
+    ) -> Result<O, XmlError>,
+{
+    let mut reader = quick_xml::Reader::from_reader(reader);
+    config.apply(reader.config_mut());
+    let mut buf = Vec::<u8>::new();
+    let transformed_root = rename_all.transform(root);
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            | Ok(Event::Start(start)) => {
+                let name = start.name().into_inner();
+                let transformed_name = rename_all.transform(name);
+                if transformed_name == transformed_root {
+                    return f(name, &mut reader, start.attributes(), false)
+                        .map_err(|e| XmlErrorWithPos::new(e, reader.buffer_position()));
+                }
+            },
+            | Ok(Event::Empty(start)) => {
+                let name = start.name().into_inner();
+                let transformed_name = rename_all.transform(name);
+                if transformed_name == transformed_root {
+                    return f(name, &mut reader, start.attributes(), true)
+                        .map_err(|e| XmlErrorWithPos::new(e, reader.buffer_position()));
+                }
+            },
+            | Ok(Event::Eof) => {
+                let pos = reader.buffer_position();
+                return Err(XmlErrorWithPos::new(missing_root(&[root]), pos));
+            },
+            | Err(e) => return Err(XmlErrorWithPos::new(e.into(), reader.error_position())),
+            | _ => {},
+        }
+    }
+}
+
+/// The entry for deserializing. `T` should have declared the `root` by `#[xmlserde(root=b"")]`
+/// to tell the deserializer which tag is the start for deserializing.
+/// Both `&str` and `String` are accepted.
+/// ```ignore
+/// use xmlserde_derives::XmlDeserialize;
+/// #[derive(XmlDeserialize)]
+/// #[xmlserde(root=b"person")]
+/// pub struct Person {
+///     #[xmlserde(name = b"age", ty = "attr")]
+///     pub age: u16,
+///     #[xmlserde(name = b"pet", ty = "child")]
+///     pub pets: Vec<Pet>,
+/// }
+/// ```
+pub fn xml_deserialize_from_str<T>(xml_str: impl AsRef<str>) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    xml_deserialize_from_reader(xml_str.as_ref().as_bytes())
+}
+
+/// Like `xml_deserialize_from_str`, but reads the bytes directly, e.g. from a network buffer.
+pub fn xml_deserialize_from_bytes<T>(bytes: &[u8]) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    xml_deserialize_from_reader(bytes)
+}
+
+/// Like `xml_deserialize_from_str`, but reads the file at `path` through a `BufReader`. The
+/// failure of opening the file is an `XmlError::ParseError` at the byte offset 0.
+pub fn xml_deserialize_from_file<T, P>(path: P) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+    P: AsRef<std::path::Path>,
+{
+    let file = std::fs::File::open(path).map_err(|e| XmlErrorWithPos::new(e.into(), 0))?;
+    xml_deserialize_from_reader(std::io::BufReader::new(file))
+}
+
+/// Lazily deserializes every element whose tag is one of the roots of `T`, at any depth of the
+/// document. Only one item is held at a time, which suits the very large documents, e.g. a dump
+/// with millions of `<record>` elements. The iterator stops after an error.
+pub fn xml_deserialize_collect_stream<'a, T, R>(
+    reader: R,
+) -> impl Iterator<Item = Result<T, XmlErrorWithPos>> + 'a
+where
+    T: XmlDeserialize + 'a,
+    R: BufRead + 'a,
+{
+    let roots = T::de_roots();
+    let rename_all = T::rename_all();
+    let mut reader = quick_xml::Reader::from_reader(reader);
+    let mut buf = Vec::<u8>::new();
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        if roots.is_empty() {
+            done = true;
+            return Some(Err(XmlErrorWithPos::new(no_root_declared(), 0)));
+        }
+        loop {
+            buf.clear();
+            let (start, is_empty) = match reader.read_event_into(&mut buf) {
+                | Ok(Event::Start(s)) => (s, false),
+                | Ok(Event::Empty(s)) => (s, true),
+                | Ok(Event::Eof) => {
+                    done = true;
+                    return None;
+                },
+                | Err(e) => {
+                    done = true;
+                    let pos = reader.error_position();
+                    return Some(Err(XmlErrorWithPos::new(e.into(), pos)));
+                },
+                | _ => continue,
+            };
+            let tag = start.name().into_inner();
+            let name = rename_all.transform(tag);
+            if roots.iter().any(|r| rename_all.transform(r) == name) {
+                let result = T::deserialize(tag, &mut reader, start.attributes(), is_empty);
+                // The position of the reader is unknown after a failure
+                done = result.is_err();
+                let pos = reader.buffer_position();
+                return Some(result.map_err(|e| XmlErrorWithPos::new(e, pos)));
+            }
+        }
+    })
+}
+
+/// Settings of the underlying `quick_xml::Reader`. The default one is the same as `quick_xml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReaderConfig {
+    /// Trims the leading and trailing whitespaces of the texts.
+    pub trim_text: bool,
+    /// Checks that the end tags match the start tags.
+    pub check_end_names: bool,
+    /// Reads `<tag/>` as `<tag></tag>`.
+    pub expand_empty_elements: bool,
+}
+
+impl Default for ReaderConfig {
+    fn default() -> Self {
+        ReaderConfig {
+            trim_text: false,
+            check_end_names: true,
+            expand_empty_elements: false,
+        }
+    }
+}
+
+impl ReaderConfig {
+    fn apply(&self, config: &mut quick_xml::reader::Config) {
+        config.trim_text(self.trim_text);
+        config.check_end_names = self.check_end_names;
+        config.expand_empty_elements = self.expand_empty_elements;
+    }
+}
+
+/// What to do with the unknown attributes and children.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownFieldBehavior {
+    /// Follows the `deny_unknown_fields`, `deny_unknown_attrs` and `deny_unknown_children` of
+    /// the types.
+    #[default]
+    AsDeclared,
+    /// Ignores the unknown fields even if the types deny them.
+    Ignore,
+}
+
+thread_local! {
+    static IGNORE_UNKNOWN_FIELDS: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    static STRICT_DUPLICATE_FIELDS: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// A helper function used by the derived code. It tells whether the unknown fields should be
+/// ignored although the type denies them.
+pub fn __ignore_unknown_fields() -> bool {
+    IGNORE_UNKNOWN_FIELDS.with(|i| i.get())
+}
+
+/// A helper function used by the derived code. It tells whether a single child appearing more
+/// than once is an error rather than overwriting the previous one.
+pub fn __strict_duplicate_fields() -> bool {
+    STRICT_DUPLICATE_FIELDS.with(|s| s.get())
+}
+
+/// Applies the `XmlDeserializeOptions` read by the derived code to the current thread until
+/// dropped.
+struct OptionsGuard {
+    prev_ignore: bool,
+    prev_strict: bool,
+}
+
+impl OptionsGuard {
+    fn new(opts: &XmlDeserializeOptions) -> Self {
+        let ignore = matches!(opts.unknown_field_behavior, UnknownFieldBehavior::Ignore);
+        let prev_ignore = IGNORE_UNKNOWN_FIELDS.with(|i| i.replace(ignore));
+        let prev_strict = STRICT_DUPLICATE_FIELDS.with(|s| s.replace(opts.strict_duplicate_fields));
+        OptionsGuard {
+            prev_ignore,
+            prev_strict,
+        }
+    }
+}
+
+impl Drop for OptionsGuard {
+    fn drop(&mut self) {
+        IGNORE_UNKNOWN_FIELDS.with(|i| i.set(self.prev_ignore));
+        STRICT_DUPLICATE_FIELDS.with(|s| s.set(self.prev_strict));
+    }
+}
+
+/// Options of deserializing at runtime, without modifying the type definitions.
+///
+/// The limits defend against the deeply nested or oversized documents from untrusted sources.
+/// They are checked before deserializing and none of them is set by default.
+/// ```ignore
+/// let opts = XmlDeserializeOptions::default()
+///     .trim_text_whitespace(true)
+///     .max_depth(64)
+///     .max_elements(100_000);
+/// let person = xml_deserialize_from_str_with_options::<Person>(xml, &opts)?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct XmlDeserializeOptions {
+    pub reader: ReaderConfig,
+    pub unknown_field_behavior: UnknownFieldBehavior,
+    /// The maximum nesting depth of the elements, where the root element is at depth 1.
+    pub max_depth: Option<usize>,
+    /// The maximum number of elements in the document.
+    pub max_elements: Option<usize>,
+    /// The maximum length in bytes of a text or an attribute value.
+    pub max_string_len: Option<usize>,
+    /// Makes an attribute or a single child appearing more than once an error. Otherwise the
+    /// last one wins.
+    pub strict_duplicate_fields: bool,
+}
+
+impl XmlDeserializeOptions {
+    /// Sets `trim_text` of the `reader`.
+    pub fn trim_text_whitespace(mut self, trim: bool) -> Self {
+        self.reader.trim_text = trim;
+        self
+    }
+
+    pub fn unknown_field_behavior(mut self, behavior: UnknownFieldBehavior) -> Self {
+        self.unknown_field_behavior = behavior;
+        self
+    }
+
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    pub fn max_elements(mut self, max: usize) -> Self {
+        self.max_elements = Some(max);
+        self
+    }
+
+    pub fn max_string_len(mut self, max: usize) -> Self {
+        self.max_string_len = Some(max);
+        self
+    }
+
+    pub fn strict_duplicate_fields(mut self, strict: bool) -> Self {
+        self.strict_duplicate_fields = strict;
+        self
+    }
+
+    fn has_checks(&self) -> bool {
+        self.max_depth.is_some()
+            || self.max_elements.is_some()
+            || self.max_string_len.is_some()
+            || self.strict_duplicate_fields
+    }
+}
+
+/// Scans `buf` for the limits of `opts` and the duplicate attributes. The malformed parts are
+/// left to the deserializing, which reports them.
+fn check_limits(buf: &[u8], opts: &XmlDeserializeOptions) -> Result<(), XmlErrorWithPos> {
+    let mut reader = quick_xml::Reader::from_reader(buf);
+    opts.reader.apply(reader.config_mut());
+    let mut event_buf = Vec::<u8>::new();
+    let mut depth = 0usize;
+    let mut elements = 0usize;
+    let exceeds = |limit: Option<usize>, n: usize| limit.is_some_and(|max| n > max);
+    loop {
+        event_buf.clear();
+        let offset = reader.buffer_position();
+        let err = |inner| Err(XmlErrorWithPos::new(inner, offset));
+        let limit = |limit: &'static str, max: Option<usize>| {
+            err(XmlError::LimitExceeded {
+                limit,
+                max: max.unwrap_or_default(),
+            })
+        };
+        let (start, is_empty) = match reader.read_event_into(&mut event_buf) {
+            | Ok(Event::Start(s)) => (s, false),
+            | Ok(Event::Empty(s)) => (s, true),
+            | Ok(Event::End(_)) => {
+                depth = depth.saturating_sub(1);
+                continue;
+            },
+            | Ok(Event::Text(t)) if exceeds(opts.max_string_len, t.len()) => {
+                return limit("max_string_len", opts.max_string_len)
+            },
+            | Ok(Event::CData(t)) if exceeds(opts.max_string_len, t.len()) => {
+                return limit("max_string_len", opts.max_string_len)
+            },
+            | Ok(Event::Eof) | Err(_) => return Ok(()),
+            | _ => continue,
+        };
+        elements += 1;
+        if exceeds(opts.max_elements, elements) {
+            return limit("max_elements", opts.max_elements);
+        }
+        if exceeds(opts.max_depth, depth + 1) {
+            return limit("max_depth", opts.max_depth);
+        }
+        if !is_empty {
+            depth += 1;
+        }
+        let mut keys = Vec::<&[u8]>::new();
+        for attr in start.attributes().with_checks(false).flatten() {
+            if exceeds(opts.max_string_len, attr.value.len()) {
+                return limit("max_string_len", opts.max_string_len);
+            }
+            let key = attr.key.into_inner();
+            if opts.strict_duplicate_fields && keys.contains(&key) {
+                let key = String::from_utf8_lossy(key).into_owned();
+                return err(XmlError::DuplicateField(key));
+            }
+            keys.push(key);
+        }
+    }
+}
+
+/// Like `xml_deserialize_from_str`, but with the given options.
+/// ```ignore
+/// let opts = XmlDeserializeOptions {
+///     reader: ReaderConfig {
+///         trim_text: true,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let person = xml_deserialize_from_str_with_options::<Person>(xml, &opts)?;
+/// ```
+pub fn xml_deserialize_from_str_with_options<T>(
+    xml: &str,
+    opts: &XmlDeserializeOptions,
+) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    let roots = T::de_roots();
+    if roots.is_empty() {
+        return Err(XmlErrorWithPos::new(no_root_declared(), 0));
+    }
+    deserialize_from_roots(&roots, xml.as_bytes(), opts)
+}
+
+/// Like `xml_deserialize_from_str`, but ignores all the unknown attributes and children even if
+/// the types deny them. It suits the XML from the external services which may add extensions.
+pub fn xml_deserialize_from_str_lenient<T>(xml: &str) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    let opts = XmlDeserializeOptions {
+        unknown_field_behavior: UnknownFieldBehavior::Ignore,
+        ..Default::default()
+    };
+    xml_deserialize_from_str_with_options(xml, &opts)
+}
+
+/// Like `xml_deserialize_from_str`, but calls `fallback` to get the value if the deserializing
+/// fails. It is common in loading configurations where a missing or corrupt file should
+/// result in the default settings.
+/// ```ignore
+/// let config = xml_deserialize_from_str_with_fallback(&xml, Config::default);
+/// ```
+pub fn xml_deserialize_from_str_with_fallback<T, F>(xml: &str, fallback: F) -> T
+where
+    T: XmlDeserialize,
+    F: FnOnce() -> T,
+{
+    xml_deserialize_from_str(xml).unwrap_or_else(|_| fallback())
+}
+
+/// Checks that `xml` is well-formed without deserializing it into a certain type: the syntax
+/// is valid, including the attributes, the entity references and the comments, the tags are
+/// balanced and there is exactly one root element. It helps to reject
+/// the malformed content from untrusted sources before the typed deserialization.
+pub fn xml_validate_wellformed(xml: &str) -> Result<(), XmlErrorWithPos> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut depth = 0usize;
+    let mut roots = 0usize;
+    loop {
+        let event = match reader.read_event() {
+            | Ok(event) => event,
+            | Err(e) => return Err(XmlErrorWithPos::new(e.into(), reader.error_position())),
+        };
+        let err = |e| Err(XmlErrorWithPos::new(e, reader.buffer_position()));
+        // The duplicate and unquoted attributes and the undefined entities are errors as well
+        let checked = match &event {
+            | Event::Start(s) | Event::Empty(s) => s.attributes().try_for_each(|attr| {
+                attr.map_err(quick_xml::Error::from)?.unescape_value()?;
+                Ok(())
+            }),
+            | Event::Text(t) => t.unescape().map(|_| ()),
+            | _ => Ok(()),
+        };
+        if let Err(e) = checked {
+            return err(XmlError::ParseError(e));
+        }
+        match event {
+            | Event::Start(_) | Event::Empty(_) if depth == 0 && roots > 0 => {
+                return err(XmlError::new("Multiple root elements"))
+            },
+            | Event::Start(_) => {
+                roots += 1;
+                depth += 1;
+            },
+            | Event::Empty(_) => roots += 1,
+            | Event::End(_) => depth -= 1,
+            | Event::Text(t) if depth == 0 && !t.iter().all(u8::is_ascii_whitespace) => {
+                return err(XmlError::new("Text outside of the root element"))
+            },
+            // A comment cannot contain `--` nor end with `-`, which would make `--->`
+            | Event::Comment(c) if c.windows(2).any(|w| w == b"--") || c.ends_with(b"-") => {
+                return err(XmlError::new("`--` inside a comment"))
+            },
+            | Event::Eof if depth > 0 => return err(XmlError::Eof),
+            | Event::Eof if roots == 0 => return err(XmlError::MissingRoot { expected: vec![] }),
+            | Event::Eof => return Ok(()),
+            | _ => {},
+        }
+    }
+}
+
+/// Merges the root element of `overlay` into the root element of `base` and returns the
+/// serialized result. The attributes of `overlay` overwrite those of `base` and the children of
+/// `overlay` are appended. The root tag of `base` is kept.
+///
+/// It is helpful in merging the configurations, like a base one with the environment-specific
+/// overrides.
+/// ```ignore
+/// let merged = xml_merge_documents(r#"<c a="1"><x/></c>"#, r#"<c a="2" b="3"><y/></c>"#)?;
+/// assert_eq!(merged, r#"<c a="2" b="3"><x/><y/></c>"#);
+/// ```
+pub fn xml_merge_documents(base: &str, overlay: &str) -> Result<String, XmlError> {
+    let (tag, mut base) = read_root_unparsed(base)?;
+    let (_, overlay) = read_root_unparsed(overlay)?;
+    overlay.attrs.into_iter().for_each(|(k, v)| {
+        match base.attrs.iter_mut().find(|(key, _)| *key == k) {
+            | Some((_, value)) => *value = v,
+            | None => base.attrs.push((k, v)),
+        }
+    });
+    base.data.extend(overlay.data);
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    base.serialize(&tag, &mut writer);
+    String::from_utf8(writer.into_inner()).map_err(|e| XmlError::new(e.to_string()))
+}
+
+/// Reads the first element of `xml` as an `Unparsed`, along with its tag.
+fn read_root_unparsed(xml: &str) -> Result<(Vec<u8>, Unparsed), XmlError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            | Ok(Event::Start(s)) => {
+                let tag = s.name().into_inner().to_vec();
+                let unparsed = Unparsed::deserialize(&tag, &mut reader, s.attributes(), false)?;
+                return Ok((tag, unparsed));
+            },
+            | Ok(Event::Empty(s)) => {
+                let tag = s.name().into_inner().to_vec();
+                let unparsed = Unparsed::deserialize(&tag, &mut reader, s.attributes(), true)?;
+                return Ok((tag, unparsed));
+            },
+            | Ok(Event::Eof) => return Err(XmlError::MissingRoot { expected: vec![] }),
+            | Err(e) => return Err(e.into()),
+            | _ => {},
+        }
+    }
+}
+
+/// A difference found by `xml_diff`. The `path` is XPath-like, e.g. `/config/db[2]/@name`, where
+/// the index is only given if there are several siblings with the same tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XmlDiffItem {
+    /// The attribute is changed, added or removed. A missing attribute is an empty string.
+    AttributeChanged {
+        path: String,
+        old: String,
+        new: String,
+    },
+    ElementAdded {
+        path: String,
+    },
+    ElementRemoved {
+        path: String,
+    },
+    /// The trimmed text of the element is changed.
+    TextChanged {
+        path: String,
+        old: String,
+        new: String,
+    },
+}
+
+/// Compares two XML documents semantically: the formatting, the attribute order and the
+/// whitespaces around the texts are ignored. The children with the same tag are compared in
+/// order. It is useful for the testing and the audit logging.
+/// ```ignore
+/// let diff = xml_diff(r#"<a x="1"><b/></a>"#, r#"<a x="2"/>"#);
+/// assert_eq!(diff, vec![
+///     XmlDiffItem::AttributeChanged { path: "/a/@x".into(), old: "1".into(), new: "2".into() },
+///     XmlDiffItem::ElementRemoved { path: "/a/b".into() },
+/// ]);
+/// ```
+pub fn xml_diff(a: &str, b: &str) -> Vec<XmlDiffItem> {
+    let mut result = Vec::new();
+    match (XmlNode::parse(a), XmlNode::parse(b)) {
+        | (Some(a), Some(b)) if a.name == b.name => {
+            let path = format!("/{}", a.name);
+            diff_nodes(&a, &b, &path, &mut result)
+        },
+        | (a, b) => {
+            if let Some(a) = a {
+                let path = format!("/{}", a.name);
+                result.push(XmlDiffItem::ElementRemoved { path });
+            }
+            if let Some(b) = b {
+                let path = format!("/{}", b.name);
+                result.push(XmlDiffItem::ElementAdded { path });
+            }
+        },
+    }
+    result
+}
+
+fn diff_nodes(a: &XmlNode, b: &XmlNode, path: &str, result: &mut Vec<XmlDiffItem>) {
+    let find = |node: &XmlNode, k: &str| {
+        node.attrs
+            .iter()
+            .find(|(key, _)| key == k)
+            .map(|(_, v)| v.clone())
+            .unwrap_or_default()
+    };
+    let keys = a
+        .attrs
+        .iter()
+        .chain(
+            b.attrs
+                .iter()
+                .filter(|(k, _)| !a.attrs.iter().any(|(key, _)| key == k)),
+        )
+        .map(|(k, _)| k);
+    keys.for_each(|k| {
+        let (old, new) = (find(a, k), find(b, k));
+        if old != new {
+            let path = format!("{}/@{}", path, k);
+            result.push(XmlDiffItem::AttributeChanged { path, old, new });
+        }
+    });
+    if a.text != b.text {
+        result.push(XmlDiffItem::TextChanged {
+            path: path.to_string(),
+            old: a.text.clone(),
+            new: b.text.clone(),
+        });
+    }
+    let mut names = Vec::<&str>::new();
+    a.children.iter().chain(b.children.iter()).for_each(|c| {
+        if !names.contains(&c.name.as_str()) {
+            names.push(&c.name);
+        }
+    });
+    names.into_iter().for_each(|name| {
+        let a_children = a
+            .children
+            .iter()
+            .filter(|c| c.name == name)
+            .collect::<Vec<_>>();
+        let b_children = b
+            .children
+            .iter()
+            .filter(|c| c.name == name)
+            .collect::<Vec<_>>();
+        let count = a_children.len().max(b_children.len());
+        (0..count).for_each(|i| {
+            let path = if count > 1 {
+                format!("{}/{}[{}]", path, name, i + 1)
+            } else {
+                format!("{}/{}", path, name)
+            };
+            match (a_children.get(i), b_children.get(i)) {
+                | (Some(a), Some(b)) => diff_nodes(a, b, &path, result),
+                | (Some(_), None) => result.push(XmlDiffItem::ElementRemoved { path }),
+                | (None, _) => result.push(XmlDiffItem::ElementAdded { path }),
+            }
+        });
+    });
+}
+
+/// A simple element tree used for comparing documents.
+struct XmlNode {
+    name: String,
+    attrs: Vec<(String, String)>,
+    text: String,
+    children: Vec<XmlNode>,
+}
+
+impl XmlNode {
+    /// Parses the root element. The malformed content after the error is ignored.
+    fn parse(xml: &str) -> Option<XmlNode> {
+        let mut reader = quick_xml::Reader::from_str(xml);
+        let mut stack = Vec::<XmlNode>::new();
+        loop {
+            let node = match reader.read_event() {
+                | Ok(Event::Start(s)) => {
+                    stack.push(XmlNode::new(&s));
+                    continue;
+                },
+                | Ok(Event::Empty(s)) => XmlNode::new(&s),
+                | Ok(Event::End(_)) => match stack.pop() {
+                    | Some(mut n) => {
+                        n.text = n.text.trim().to_string();
+                        n
+                    },
+                    | None => return None,
+                },
+                | Ok(Event::Text(t)) => {
+                    if let Some(n) = stack.last_mut() {
+                        match t.unescape() {
+                            | Ok(t) => n.text.push_str(&t),
+                            | Err(_) => n.text.push_str(&String::from_utf8_lossy(&t)),
+                        }
+                    }
+                    continue;
+                },
+                | Ok(Event::CData(t)) => {
+                    if let Some(n) = stack.last_mut() {
+                        n.text.push_str(&String::from_utf8_lossy(&t));
+                    }
+                    continue;
+                },
+                | Ok(Event::Eof) | Err(_) => {
+                    // Close the unclosed elements
+                    let mut node = stack.pop()?;
+                    while let Some(mut parent) = stack.pop() {
+                        parent.children.push(node);
+                        node = parent;
+                    }
+                    return Some(node);
+                },
+                | _ => continue,
+            };
+            match stack.last_mut() {
+                | Some(parent) => parent.children.push(node),
+                | None => return Some(node),
+            }
+        }
+    }
+
+    fn new(start: &quick_xml::events::BytesStart) -> XmlNode {
+        let attrs = start
+            .attributes()
+            .flatten()
+            .map(|a| {
+                let k = String::from_utf8_lossy(a.key.into_inner()).into_owned();
+                let v = match a.unescape_value() {
+                    | Ok(v) => v.into_owned(),
+                    | Err(_) => String::from_utf8_lossy(&a.value).into_owned(),
+                };
+                (k, v)
+            })
+            .collect();
+        XmlNode {
+            name: String::from_utf8_lossy(start.name().into_inner()).into_owned(),
+            attrs,
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// A helper function used by the derived code to convert the children collected for a `[T; N]`
+/// field. It fails if there are not exactly `N` children.
+pub fn __into_array<T, const N: usize>(v: Vec<T>, name: &str) -> Result<[T; N], XmlError> {
+    use std::convert::TryFrom;
+    let len = v.len();
+    <[T; N]>::try_from(v)
+        .map_err(|_| XmlError::new(format!("expected {} '{}' elements, found {}", N, name, len)))
+}
+
+/// A helper function used by the derived code to serialize a `flatten` field. It returns the raw
+/// attributes of the element `value` is serialized as.
+pub fn __flatten_attrs<T: XmlSerialize>(value: &T) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    value.serialize(b"_", &mut writer);
+    let buf = writer.into_inner();
+    let mut reader = quick_xml::Reader::from_reader(buf.as_slice());
+    let mut ev_buf = Vec::new();
+    match reader.read_event_into(&mut ev_buf) {
+        | Ok(Event::Start(s)) | Ok(Event::Empty(s)) => s
+            .attributes()
+            .flatten()
+            .map(|a| (a.key.into_inner().to_vec(), a.value.into_owned()))
+            .collect(),
+        | _ => Vec::new(),
+    }
+}
+
+/// A helper function used by the derived code to deserialize a `flatten` field from the raw
+/// attributes left by the other fields of the element `tag`.
+pub fn __deserialize_flattened<T: XmlDeserialize>(
+    tag: &[u8],
+    attrs: &[(Vec<u8>, Vec<u8>)],
+) -> Result<T, XmlError> {
+    let mut start = quick_xml::events::BytesStart::new(String::from_utf8_lossy(tag));
+    for (k, v) in attrs {
+        start.push_attribute((k.as_slice(), v.as_slice()));
+    }
+    let mut reader = quick_xml::Reader::from_reader(&b""[..]);
+    T::deserialize(tag, &mut reader, start.attributes(), true)
+}
+
+/// A helper function used by the derived code to write a `cdata` field. A `]]>` in the text
+/// would end the section, so the text is split there into several sections.
+pub fn __write_cdata<W: Write>(writer: &mut quick_xml::Writer<W>, text: &str) {
+    let mut rest = text;
+    while let Some(i) = rest.find("]]>") {
+        // `]]` ends this section and `>` starts the next one
+        let _ = writer.write_event(Event::CData(quick_xml::events::BytesCData::new(
+            &rest[..i + 2],
+        )));
+        rest = &rest[i + 2..];
+    }
+    let _ = writer.write_event(Event::CData(quick_xml::events::BytesCData::new(rest)));
+}
+
+/// A helper function used by the derived code when meeting an unknown attribute or child.
+///
+/// With the `log` feature, it warns about the unknown `name` so that the forgotten fields can be
+/// discovered without `deny_unknown_fields`. Otherwise it does nothing.
+#[allow(unused_variables)]
+pub fn __warn_unknown(kind: &str, name: &[u8], tag: &[u8]) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        "xmlserde: unknown {} '{}' in <{}>",
+        kind,
+        String::from_utf8_lossy(name),
+        String::from_utf8_lossy(tag)
+    );
+}
+
+/// The async entry for deserializing from a file, available with the `tokio` feature.
+///
+/// The file is read through `tokio` so that the runtime is not blocked while loading, and
+/// then it is deserialized like `xml_deserialize_from_reader`.
+#[cfg(feature = "tokio")]
+pub async fn xml_deserialize_from_tokio_file<T>(
+    path: impl AsRef<std::path::Path>,
+) -> Result<T, XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    use tokio::io::AsyncReadExt;
+
+    let io_error = |e: std::io::Error| XmlErrorWithPos::new(e.into(), 0);
+    let file = tokio::fs::File::open(path).await.map_err(io_error)?;
+    let mut reader = tokio::io::BufReader::new(file);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.map_err(io_error)?;
+    xml_deserialize_from_reader(buf.as_slice())
+}
+
+/// Deserializes the XML into an existing value. Only the fields appearing in the XML are
+/// overwritten and the others keep their current values, which is helpful for applying
+/// partial updates to a configuration. If it fails, `existing` is left unchanged.
+///
+/// Like `xml_deserialize_from_str`, `T` should have declared the `root` by
+/// `#[xmlserde(root=b"")]`.
+/// ```ignore
+/// let mut config = xml_deserialize_from_str::<Config>(base)?;
+/// xml_deserialize_into(&mut config, r#"<config port="8080"/>"#)?;
+/// ```
+pub fn xml_deserialize_into<T>(existing: &mut T, xml: &str) -> Result<(), XmlErrorWithPos>
+where
+    T: XmlDeserialize,
+{
+    let roots = T::de_roots();
+    if roots.is_empty() {
+        return Err(XmlErrorWithPos::new(no_root_declared(), 0));
+    }
+    let config = ReaderConfig::default();
+    let buf = xml.as_bytes();
+    let (idx, offset) = find_first_root(buf, &roots, T::rename_all(), &config)?;
+    read_from_root(
+        &buf[offset..],
+        roots[idx],
+        T::rename_all(),
+        &config,
+        |t, r, a, b| existing.__deserialize_into(t, r, a, b),
+    )
+    .map_err(|e| XmlErrorWithPos::new(e.inner, e.byte_offset + offset as u64))
+}
+
+/// A push-parser style alternative to `XmlDeserialize`.
+///
+/// Instead of building a typed struct, `xml_visit` walks through the XML element and calls
+/// back the visitor, which is helpful for streaming transformations.
+pub trait XmlVisitor {
+    /// Called for every attribute of the root element and the entered child elements.
+    fn on_attr(&mut self, name: &[u8], value: &str);
+    /// Called when meeting a child element. Returning `false` skips this element and all of
+    /// its descendants.
+    fn on_child_start(&mut self, tag: &[u8]) -> bool;
+    /// Called when leaving a child element which was entered.
+    fn on_child_end(&mut self, tag: &[u8]);
+    /// Called for the text and CDATA contents. Whitespaces are kept as they are.
+    fn on_text(&mut self, text: &str);
+}
+
+/// Looks for the `root` element in the XML and walks through it with the visitor.
+/// ```ignore
+/// struct TagCounter(usize);
+///
+/// impl XmlVisitor for TagCounter {
+///     fn on_attr(&mut self, _: &[u8], _: &str) {}
+///     fn on_child_start(&mut self, _: &[u8]) -> bool {
+///         self.0 += 1;
+///         true
+///     }
+///     fn on_child_end(&mut self, _: &[u8]) {}
+///     fn on_text(&mut self, _: &str) {}
+/// }
+///
+/// let mut counter = TagCounter(0);
+/// xml_visit(r#"<person><pet/><pet/></person>"#, b"person", &mut counter)?;
+/// ```
+pub fn xml_visit<V>(xml: &str, root: &[u8], visitor: &mut V) -> Result<(), XmlErrorWithPos>
+where
+    V: XmlVisitor,
+{
+    read_from_root(
+        xml.as_bytes(),
+        root,
+        Case::None,
+        &ReaderConfig::default(),
+        |_, reader, attrs, is_empty| visit_element(reader, attrs, is_empty, visitor),
+    )
+}
+
+fn visit_attrs<V: XmlVisitor>(
+    attrs: quick_xml::events::attributes::Attributes,
+    visitor: &mut V,
+) -> Result<(), XmlError> {
+    for attr in attrs {
+        let attr = attr.map_err(quick_xml::Error::from)?;
+        let value = attr.unescape_value()?;
+        visitor.on_attr(attr.key.into_inner(), &value);
+    }
+    Ok(())
+}
+
+fn visit_element<B: BufRead, V: XmlVisitor>(
+    reader: &mut quick_xml::Reader<B>,
+    attrs: quick_xml::events::attributes::Attributes,
+    is_empty: bool,
+    visitor: &mut V,
+) -> Result<(), XmlError> {
+    visit_attrs(attrs, visitor)?;
+    if is_empty {
+        return Ok(());
+    }
+    let mut buf = Vec::<u8>::new();
+    let mut depth = 0usize;
+    loop {
+        match reader.read_event_into(&mut buf) {
+            | Ok(Event::Start(s)) => {
+                if visitor.on_child_start(s.name().into_inner()) {
+                    visit_attrs(s.attributes(), visitor)?;
+                    depth += 1;
+                } else {
+                    let end = s.to_end().into_owned();
+                    reader.read_to_end_into(end.name(), &mut Vec::new())?;
+                }
+            },
+            | Ok(Event::Empty(s)) => {
+                let tag = s.name().into_inner();
+                if visitor.on_child_start(tag) {
+                    visit_attrs(s.attributes(), visitor)?;
+                    visitor.on_child_end(tag);
+                }
+            },
+            | Ok(Event::End(e)) => {
+                if depth == 0 {
+                    return Ok(());
+                }
+                depth -= 1;
+                visitor.on_child_end(e.name().into_inner());
+            },
+            | Ok(Event::Text(t)) => {
+                let text = t.unescape()?;
+                visitor.on_text(&text);
+            },
+            | Ok(Event::CData(c)) => visitor.on_text(&String::from_utf8_lossy(&c)),
+            | Ok(Event::Eof) => return Err(XmlError::Eof),
+            | Err(e) => return Err(e.into()),
+            | _ => {},
+        }
+        buf.clear();
+    }
+}
+
+pub trait XmlValue: Sized {
+    fn serialize(&self) -> String;
+    fn deserialize(s: &str) -> Result<Self, String>;
+}
+
+/// `None` is serialized as an empty string and an empty string is deserialized as `None`.
+impl<T: XmlValue> XmlValue for Option<T> {
+    fn serialize(&self) -> String {
+        match self {
+            | Some(v) => v.serialize(),
+            | None => String::new(),
+        }
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        if s.is_empty() {
+            Ok(None)
+        } else {
+            T::deserialize(s).map(Some)
+        }
+    }
+}
+
+impl XmlValue for bool {
+    fn serialize(&self) -> String {
         if *self {
             String::from("1")
         } else {
@@ -434,36 +2345,617 @@ impl XmlValue for bool {
     }
 
     fn deserialize(s: &str) -> Result<Self, String> {
-        let s = s.to_ascii_lowercase();
-        if s == "1" || s == "true" {
-            Ok(true)
-        } else if s == "0" || s == "false" {
-            Ok(false)
-        } else {
-            Err(format!("Cannot parse {} into a boolean", s))
+        let s = s.to_ascii_lowercase();
+        if s == "1" || s == "true" {
+            Ok(true)
+        } else if s == "0" || s == "false" {
+            Ok(false)
+        } else {
+            Err(format!("Cannot parse {} into a boolean", s))
+        }
+    }
+}
+
+impl XmlValue for String {
+    fn serialize(&self) -> String {
+        self.to_owned()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(s.to_owned())
+    }
+}
+
+impl XmlValue for std::borrow::Cow<'static, str> {
+    fn serialize(&self) -> String {
+        AsRef::<str>::as_ref(self).to_owned()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(std::borrow::Cow::Owned(s.to_owned()))
+    }
+}
+
+impl XmlValue for char {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            | (Some(c), None) => Ok(c),
+            | _ => Err(format!("Cannot parse {} into a char", s)),
+        }
+    }
+}
+
+impl XmlValue for std::path::PathBuf {
+    fn serialize(&self) -> String {
+        self.to_string_lossy().into_owned()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(std::path::PathBuf::from(s))
+    }
+}
+
+/// A decimal number kept in its exact string form, like `-12.340`. It fits the financial
+/// formats (XBRL, FpML, ISO 20022) where neither `f64` nor integers represent the values exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FixedDecimal(pub String);
+
+impl XmlValue for FixedDecimal {
+    fn serialize(&self) -> String {
+        self.0.clone()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        let digits = |v: &str| !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit());
+        let unsigned = s.strip_prefix('-').unwrap_or(s);
+        let valid = match unsigned.split_once('.') {
+            | Some((int, frac)) => digits(int) && digits(frac),
+            | None => digits(unsigned),
+        };
+        if valid {
+            Ok(FixedDecimal(s.to_owned()))
+        } else {
+            Err(format!("Cannot parse {} into a fixed decimal", s))
+        }
+    }
+}
+
+/// A `Duration` in the fractional seconds, like `90.5`. It is used by the media formats like
+/// TTML and SMIL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DurationSeconds(pub std::time::Duration);
+
+impl From<std::time::Duration> for DurationSeconds {
+    fn from(d: std::time::Duration) -> Self {
+        DurationSeconds(d)
+    }
+}
+
+impl XmlValue for DurationSeconds {
+    fn serialize(&self) -> String {
+        format_secs(self.0.as_secs(), self.0.subsec_nanos())
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        // The plain decimals are parsed exactly and the others, like `1e3`, as a float
+        if let Some(nanos) = parse_decimal_nanos(s, 1) {
+            return nanos_to_duration(nanos)
+                .map(DurationSeconds)
+                .ok_or_else(|| format!("Cannot parse {} into a duration", s));
+        }
+        let secs = s.parse::<f64>().map_err(|e| e.to_string())?;
+        std::time::Duration::try_from_secs_f64(secs)
+            .map(DurationSeconds)
+            .map_err(|e| format!("Cannot parse {} into a duration: {}", s, e))
+    }
+}
+
+/// A `Duration` written in the decimal seconds like `DurationSeconds`, the counterpart of
+/// `IsoDuration`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SecondsDuration(pub std::time::Duration);
+
+impl From<std::time::Duration> for SecondsDuration {
+    fn from(d: std::time::Duration) -> Self {
+        SecondsDuration(d)
+    }
+}
+
+impl XmlValue for SecondsDuration {
+    fn serialize(&self) -> String {
+        DurationSeconds(self.0).serialize()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        DurationSeconds::deserialize(s).map(|d| SecondsDuration(d.0))
+    }
+}
+
+/// A `Duration` written as an ISO 8601 duration, like `PT30.5S` or `P1DT2H3M4.567S`.
+///
+/// It is written in days, hours, minutes and seconds. All the components are read, where a
+/// year is 365 days, a month is 30 days and a week is 7 days since a `Duration` has no
+/// calendar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IsoDuration(pub std::time::Duration);
+
+impl From<std::time::Duration> for IsoDuration {
+    fn from(d: std::time::Duration) -> Self {
+        IsoDuration(d)
+    }
+}
+
+impl XmlValue for IsoDuration {
+    fn serialize(&self) -> String {
+        let secs = self.0.as_secs();
+        let nanos = self.0.subsec_nanos();
+        let (days, hours, minutes, secs) = (
+            secs / 86400,
+            secs % 86400 / 3600,
+            secs % 3600 / 60,
+            secs % 60,
+        );
+        let mut r = String::from("P");
+        if days > 0 {
+            r.push_str(&format!("{}D", days));
+        }
+        if hours == 0 && minutes == 0 && secs == 0 && nanos == 0 {
+            if days == 0 {
+                r.push_str("T0S");
+            }
+            return r;
+        }
+        r.push('T');
+        if hours > 0 {
+            r.push_str(&format!("{}H", hours));
+        }
+        if minutes > 0 {
+            r.push_str(&format!("{}M", minutes));
+        }
+        if secs > 0 || nanos > 0 {
+            r.push_str(&format!("{}S", format_secs(secs, nanos)));
+        }
+        r
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        const DAY: u64 = 86400;
+        let err = || format!("Cannot parse {} into an ISO 8601 duration", s);
+        let rest = s.trim().strip_prefix('P').ok_or_else(err)?;
+        let (date, time) = match rest.split_once('T') {
+            | Some((_, "")) => return Err(err()),
+            | Some((date, time)) => (date, time),
+            | None => (rest, ""),
+        };
+        if date.is_empty() && time.is_empty() {
+            return Err(err());
+        }
+        let date_units: &[(char, u64)] = &[
+            ('Y', 365 * DAY),
+            ('M', 30 * DAY),
+            ('W', 7 * DAY),
+            ('D', DAY),
+        ];
+        let time_units: &[(char, u64)] = &[('H', 3600), ('M', 60), ('S', 1)];
+        let mut nanos = 0u128;
+        for (mut part, units) in [(date, date_units), (time, time_units)] {
+            // The components are in the order of `units`, each at most once
+            let mut next = 0;
+            while !part.is_empty() {
+                let end = part
+                    .find(|c: char| c.is_ascii_alphabetic())
+                    .ok_or_else(err)?;
+                let unit = part[end..].chars().next().ok_or_else(err)?;
+                let idx = units[next..]
+                    .iter()
+                    .position(|(u, _)| *u == unit)
+                    .ok_or_else(err)?;
+                let (_, unit_secs) = units[next + idx];
+                next += idx + 1;
+                nanos = parse_decimal_nanos(&part[..end], unit_secs)
+                    .and_then(|n| nanos.checked_add(n))
+                    .ok_or_else(err)?;
+                part = &part[end + 1..];
+            }
+        }
+        nanos_to_duration(nanos).map(IsoDuration).ok_or_else(err)
+    }
+}
+
+/// Writes the seconds like `30`, `30.5` or `0.000001`, without the trailing zeros.
+fn format_secs(secs: u64, nanos: u32) -> String {
+    if nanos == 0 {
+        return secs.to_string();
+    }
+    let frac = format!("{:09}", nanos);
+    format!("{}.{}", secs, frac.trim_end_matches('0'))
+}
+
+/// Parses a non-negative decimal like `4.567` or `4,567` of `unit_secs` into nanoseconds. The
+/// digits beyond the nanoseconds are truncated.
+fn parse_decimal_nanos(s: &str, unit_secs: u64) -> Option<u128> {
+    let (int, frac) = match s.find(['.', ',']) {
+        | Some(i) => (&s[..i], &s[i + 1..]),
+        | None => (s, ""),
+    };
+    let all_digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty()
+        || !all_digits(int)
+        || !all_digits(frac)
+        || (s.len() > int.len() && frac.is_empty())
+    {
+        return None;
+    }
+    let unit = unit_secs as u128 * 1_000_000_000;
+    let int = int.parse::<u128>().ok()?.checked_mul(unit)?;
+    let frac = &frac[..frac.len().min(18)];
+    let frac_value = if frac.is_empty() {
+        0
+    } else {
+        frac.parse::<u128>().ok()?
+    };
+    int.checked_add(frac_value * unit / 10u128.pow(frac.len() as u32))
+}
+
+fn nanos_to_duration(nanos: u128) -> Option<std::time::Duration> {
+    use std::convert::TryFrom;
+    let secs = u64::try_from(nanos / 1_000_000_000).ok()?;
+    Some(std::time::Duration::new(
+        secs,
+        (nanos % 1_000_000_000) as u32,
+    ))
+}
+
+/// A value written and parsed by its `Display` and `FromStr` impls, which saves implementing
+/// `XmlValue` for the external types. Alternatively, `xml_value = "display_fromstr"` on an
+/// `attr` or `text` field does the same without wrapping the field type.
+/// ```ignore
+/// #[xmlserde(name = b"id", ty = "attr")]
+/// id: XmlValueFromStr<uuid::Uuid>,
+/// #[xmlserde(name = b"ver", ty = "attr", xml_value = "display_fromstr")]
+/// version: semver::Version,
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct XmlValueFromStr<T>(pub T);
+
+impl<T> XmlValue for XmlValueFromStr<T>
+where
+    T: std::fmt::Display + std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    fn serialize(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        s.parse::<T>()
+            .map(XmlValueFromStr)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// A helper function used by the derived code for `xml_value = "display_fromstr"`.
+pub fn __display_serialize<T: std::fmt::Display>(v: &T) -> String {
+    v.to_string()
+}
+
+/// A helper function used by the derived code for `xml_value = "display_fromstr"`.
+pub fn __from_str_deserialize<T>(s: &str) -> Result<T, String>
+where
+    T: std::fmt::Display + std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    XmlValueFromStr::<T>::deserialize(s).map(|v| v.0)
+}
+
+/// Binary data encoded as the lowercase hex, like the digests in XML-DSig. Both cases are
+/// accepted when deserializing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct HexBytes(pub Vec<u8>);
+
+fn hex_digit(b: u8) -> u8 {
+    match b {
+        | b'0'..=b'9' => b - b'0',
+        | b'a'..=b'f' => b - b'a' + 10,
+        | _ => b - b'A' + 10,
+    }
+}
+
+impl XmlValue for HexBytes {
+    fn serialize(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        if !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(format!("Cannot parse {} into hex bytes", s));
+        }
+        s.as_bytes()
+            .chunks(2)
+            .map(|pair| match pair {
+                | [h, l] => Ok(hex_digit(*h) << 4 | hex_digit(*l)),
+                | _ => Err(format!("Odd number of hex digits: {}", s)),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(HexBytes)
+    }
+}
+
+/// A whitespace-separated list of values, like the `class` or `points` attributes in SVG.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SpaceSeparated<T>(pub Vec<T>);
+
+impl<T: XmlValue> XmlValue for SpaceSeparated<T> {
+    fn serialize(&self) -> String {
+        self.0
+            .iter()
+            .map(|v| v.serialize())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        s.split_whitespace()
+            .map(T::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .map(SpaceSeparated)
+    }
+}
+
+/// A comma-separated list of values, like the `font-family` in CSS. It is serialized with `", "`
+/// and the whitespaces around each item are ignored when deserializing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CommaSeparated<T>(pub Vec<T>);
+
+impl<T: XmlValue> XmlValue for CommaSeparated<T> {
+    fn serialize(&self) -> String {
+        self.0
+            .iter()
+            .map(|v| v.serialize())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        if s.trim().is_empty() {
+            return Ok(CommaSeparated(Vec::new()));
         }
+        s.split(',')
+            .map(|v| T::deserialize(v.trim()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(CommaSeparated)
     }
 }
 
-impl XmlValue for String {
+/// Serialized as the space-separated values in their sorted order. The duplicated values are
+/// dropped when deserializing, like the `class` attribute.
+impl<T: XmlValue + Ord> XmlValue for std::collections::BTreeSet<T> {
     fn serialize(&self) -> String {
-        self.to_owned()
+        self.iter()
+            .map(|v| v.serialize())
+            .collect::<Vec<_>>()
+            .join(" ")
     }
 
     fn deserialize(s: &str) -> Result<Self, String> {
-        Ok(s.to_owned())
+        s.split_whitespace().map(T::deserialize).collect()
     }
 }
 
-macro_rules! impl_xml_value_for_num {
-    ($num:ty) => {
-        impl XmlValue for $num {
+macro_rules! impl_xml_value_for_tuple {
+    ($len:literal, $($t:ident $idx:tt),+) => {
+        /// Serialized as the space-separated values, like `x y z`. Deserializing requires
+        /// exactly as many whitespace-separated parts as the tuple has elements.
+        impl<$($t: XmlValue),+> XmlValue for ($($t,)+) {
+            fn serialize(&self) -> String {
+                [$(self.$idx.serialize()),+].join(" ")
+            }
+
+            fn deserialize(s: &str) -> Result<Self, String> {
+                let parts = s.split_whitespace().collect::<Vec<_>>();
+                if parts.len() != $len {
+                    return Err(format!("Expect {} values but found {}: {}", $len, parts.len(), s));
+                }
+                Ok(($($t::deserialize(parts[$idx])?,)+))
+            }
+        }
+    };
+}
+
+impl_xml_value_for_tuple!(2, T1 0, T2 1);
+impl_xml_value_for_tuple!(3, T1 0, T2 1, T3 2);
+
+/// Serialized as the seconds since the Unix epoch, the sub-second part is dropped.
+/// Deserializing accepts the seconds or an ISO 8601 datetime.
+impl XmlValue for std::time::SystemTime {
+    fn serialize(&self) -> String {
+        let (secs, _) = split_system_time(*self);
+        secs.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        match s.trim().parse::<i64>() {
+            | Ok(secs) => system_time_from_parts(secs, 0)
+                .ok_or_else(|| format!("{} seconds is out of the range of SystemTime", secs)),
+            | Err(_) => parse_iso8601(s),
+        }
+    }
+}
+
+/// A `SystemTime` serialized as an ISO 8601 datetime in UTC, like `2024-01-02T03:04:05Z`.
+/// Deserializing accepts a datetime with an optional fraction and offset, or the seconds since
+/// the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Iso8601SystemTime(pub std::time::SystemTime);
+
+impl XmlValue for Iso8601SystemTime {
+    fn serialize(&self) -> String {
+        let (secs, nanos) = split_system_time(self.0);
+        let (date, time) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+        let (y, m, d) = civil_from_days(date);
+        let mut r = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            y,
+            m,
+            d,
+            time / 3600,
+            time % 3600 / 60,
+            time % 60
+        );
+        if nanos > 0 {
+            let frac = format!("{:09}", nanos);
+            r.push('.');
+            r.push_str(frac.trim_end_matches('0'));
+        }
+        r.push('Z');
+        r
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        <std::time::SystemTime as XmlValue>::deserialize(s).map(Iso8601SystemTime)
+    }
+}
+
+/// Splits a `SystemTime` into the seconds since the Unix epoch and the non-negative nanoseconds.
+fn split_system_time(t: std::time::SystemTime) -> (i64, u32) {
+    match t.duration_since(std::time::UNIX_EPOCH) {
+        | Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        | Err(e) => {
+            let d = e.duration();
+            match d.subsec_nanos() {
+                | 0 => (-(d.as_secs() as i64), 0),
+                | n => (-(d.as_secs() as i64) - 1, 1_000_000_000 - n),
+            }
+        },
+    }
+}
+
+/// The `SystemTime` of the seconds since the Unix epoch, or `None` if the platform cannot
+/// represent it.
+fn system_time_from_parts(secs: i64, nanos: u32) -> Option<std::time::SystemTime> {
+    let epoch = std::time::UNIX_EPOCH;
+    if secs >= 0 {
+        epoch.checked_add(std::time::Duration::new(secs as u64, nanos))
+    } else {
+        epoch
+            .checked_sub(std::time::Duration::from_secs(secs.unsigned_abs()))?
+            .checked_add(std::time::Duration::from_nanos(nanos as u64))
+    }
+}
+
+// Converts between the days since the Unix epoch and the proleptic Gregorian calendar.
+// See http://howardhinnant.github.io/date_algorithms.html
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+/// `None` if the days overflow an `i64`.
+fn days_from_civil(y: i64, m: u32, d: u32) -> Option<i64> {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = m as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era.checked_mul(146097)?.checked_add(doe - 719468)
+}
+
+/// Parses `YYYY-MM-DDTHH:MM:SS[.fraction][Z|+HH:MM|-HH:MM]`. A missing offset means UTC.
+fn parse_iso8601(s: &str) -> Result<std::time::SystemTime, String> {
+    let err = || format!("Cannot parse {} into an ISO 8601 datetime", s);
+    let num = |v: &str| -> Result<i64, String> {
+        if v.is_empty() || !v.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+        v.parse::<i64>().map_err(|_| err())
+    };
+    let (date, time) = s.trim().split_once(['T', 't', ' ']).ok_or_else(err)?;
+    let mut date_parts = date.splitn(3, '-');
+    let (y, m, d) = match (date_parts.next(), date_parts.next(), date_parts.next()) {
+        | (Some(y), Some(m), Some(d)) => (num(y)?, num(m)?, num(d)?),
+        | _ => return Err(err()),
+    };
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return Err(err());
+    }
+    let (time, offset) = if let Some(t) = time.strip_suffix(['Z', 'z']) {
+        (t, 0)
+    } else if let Some(i) = time.rfind(['+', '-']) {
+        let (hh, mm) = time[i + 1..].split_once(':').ok_or_else(err)?;
+        let (hh, mm) = (num(hh)?, num(mm)?);
+        if hh > 23 || mm > 59 {
+            return Err(err());
+        }
+        let offset = hh * 3600 + mm * 60;
+        let offset = if time[i..].starts_with('-') {
+            -offset
+        } else {
+            offset
+        };
+        (&time[..i], offset)
+    } else {
+        (time, 0)
+    };
+    let (time, nanos) = match time.split_once('.') {
+        | Some((t, frac)) => {
+            num(frac)?;
+            let digits = format!("{:0<9}", &frac[..frac.len().min(9)]);
+            (t, digits.parse::<u32>().map_err(|_| err())?)
+        },
+        | None => (time, 0),
+    };
+    let mut time_parts = time.splitn(3, ':');
+    let (hh, mm, ss) = match (time_parts.next(), time_parts.next(), time_parts.next()) {
+        | (Some(hh), Some(mm), Some(ss)) => (num(hh)?, num(mm)?, num(ss)?),
+        | _ => return Err(err()),
+    };
+    if hh > 23 || mm > 59 || ss > 60 {
+        return Err(err());
+    }
+    let secs = days_from_civil(y, m as u32, d as u32)
+        .and_then(|days| days.checked_mul(86400))
+        .and_then(|secs| secs.checked_add(hh * 3600 + mm * 60 + ss))
+        .and_then(|secs| secs.checked_sub(offset))
+        .ok_or_else(err)?;
+    system_time_from_parts(secs, nanos).ok_or_else(err)
+}
+
+impl XmlValue for std::ffi::OsString {
+    fn serialize(&self) -> String {
+        self.to_string_lossy().into_owned()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(std::ffi::OsString::from(s))
+    }
+}
+
+/// Implements `XmlValue` by the `Display` and `FromStr` impls of the type.
+macro_rules! impl_xml_value_by_display_fromstr {
+    ($t:ty) => {
+        impl XmlValue for $t {
             fn serialize(&self) -> String {
                 self.to_string()
             }
 
             fn deserialize(s: &str) -> Result<Self, String> {
-                let r = s.parse::<$num>();
+                let r = s.parse::<$t>();
                 match r {
                     | Ok(f) => Ok(f),
                     | Err(e) => Err(e.to_string()),
@@ -473,6 +2965,12 @@ macro_rules! impl_xml_value_for_num {
     };
 }
 
+macro_rules! impl_xml_value_for_num {
+    ($num:ty) => {
+        impl_xml_value_by_display_fromstr!($num);
+    };
+}
+
 impl_xml_value_for_num!(i8);
 impl_xml_value_for_num!(u8);
 impl_xml_value_for_num!(i16);
@@ -499,3 +2997,385 @@ impl_xml_value_for_num!(std::num::NonZeroI128);
 impl_xml_value_for_num!(std::num::NonZeroU128);
 impl_xml_value_for_num!(std::num::NonZeroIsize);
 impl_xml_value_for_num!(std::num::NonZeroUsize);
+
+// The scope of an IPv6 socket address is numeric, like `[fe80::1%2]:443`, since the interface
+// names are not parsed by `std`.
+impl_xml_value_by_display_fromstr!(std::net::IpAddr);
+impl_xml_value_by_display_fromstr!(std::net::Ipv4Addr);
+impl_xml_value_by_display_fromstr!(std::net::Ipv6Addr);
+impl_xml_value_by_display_fromstr!(std::net::SocketAddr);
+impl_xml_value_by_display_fromstr!(std::net::SocketAddrV4);
+impl_xml_value_by_display_fromstr!(std::net::SocketAddrV6);
+
+impl<T: XmlValue> XmlValue for std::num::Saturating<T> {
+    fn serialize(&self) -> String {
+        self.0.serialize()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        T::deserialize(s).map(std::num::Saturating)
+    }
+}
+
+impl<T: XmlValue> XmlValue for Box<T> {
+    fn serialize(&self) -> String {
+        (**self).serialize()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        T::deserialize(s).map(Box::new)
+    }
+}
+
+// The shared pointers are read and written like the values they point to, so an `Rc<T>` or
+// `Arc<T>` field works as a plain field of `T`.
+macro_rules! impl_xml_for_shared_pointer {
+    ($module:ident::$ptr:ident) => {
+        impl<T: XmlValue> XmlValue for std::$module::$ptr<T> {
+            fn serialize(&self) -> String {
+                (**self).serialize()
+            }
+
+            fn deserialize(s: &str) -> Result<Self, String> {
+                T::deserialize(s).map(std::$module::$ptr::new)
+            }
+        }
+
+        impl<T: XmlSerialize> XmlSerialize for std::$module::$ptr<T> {
+            fn serialize<W: Write>(&self, tag: &[u8], writer: &mut quick_xml::Writer<W>) {
+                (**self).serialize(tag, writer)
+            }
+
+            fn ser_roots() -> Vec<&'static [u8]> {
+                T::ser_roots()
+            }
+
+            fn __ser_static() -> Option<&'static str> {
+                T::__ser_static()
+            }
+        }
+
+        impl<T: XmlDeserialize> XmlDeserialize for std::$module::$ptr<T> {
+            fn deserialize<B: BufRead>(
+                tag: &[u8],
+                reader: &mut quick_xml::Reader<B>,
+                attrs: quick_xml::events::attributes::Attributes,
+                is_empty: bool,
+            ) -> Result<Self, XmlError> {
+                T::deserialize(tag, reader, attrs, is_empty).map(std::$module::$ptr::new)
+            }
+
+            fn de_roots() -> Vec<&'static [u8]> {
+                T::de_roots()
+            }
+
+            fn rename_all() -> Case {
+                T::rename_all()
+            }
+
+            fn __get_children_tags() -> Vec<&'static [u8]> {
+                T::__get_children_tags()
+            }
+
+            fn __deserialize_from_unparsed_array(
+                array: Vec<(&'static [u8], Unparsed)>,
+            ) -> Result<Self, XmlError> {
+                T::__deserialize_from_unparsed_array(array).map(std::$module::$ptr::new)
+            }
+
+            fn __is_enum() -> bool {
+                T::__is_enum()
+            }
+
+            fn __deserialize_from_text(s: &str) -> Result<Option<Self>, XmlError> {
+                T::__deserialize_from_text(s).map(|t| t.map(std::$module::$ptr::new))
+            }
+        }
+    };
+}
+
+impl_xml_for_shared_pointer!(rc::Rc);
+impl_xml_for_shared_pointer!(sync::Arc);
+
+impl<T: XmlValue> XmlValue for std::cmp::Reverse<T> {
+    fn serialize(&self) -> String {
+        self.0.serialize()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        T::deserialize(s).map(std::cmp::Reverse)
+    }
+}
+
+impl<T: XmlValue> XmlValue for std::ops::Range<T> {
+    fn serialize(&self) -> String {
+        format!("{}..{}", self.start.serialize(), self.end.serialize())
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        match s.split_once("..") {
+            | Some((start, end)) if !end.starts_with('=') => {
+                Ok(T::deserialize(start)?..T::deserialize(end)?)
+            },
+            | _ => Err(format!("Cannot parse {} into a range", s)),
+        }
+    }
+}
+
+impl<T: XmlValue> XmlValue for std::ops::RangeInclusive<T> {
+    fn serialize(&self) -> String {
+        format!("{}..={}", self.start().serialize(), self.end().serialize())
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        match s.split_once("..=") {
+            | Some((start, end)) => Ok(T::deserialize(start)?..=T::deserialize(end)?),
+            | None => Err(format!("Cannot parse {} into an inclusive range", s)),
+        }
+    }
+}
+
+#[cfg(feature = "smol-str")]
+impl XmlValue for smol_str::SmolStr {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(smol_str::SmolStr::new(s))
+    }
+}
+
+#[cfg(feature = "compact-str")]
+impl XmlValue for compact_str::CompactString {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(compact_str::CompactString::new(s))
+    }
+}
+
+/// Available with the `decimal` feature. The values are kept exactly, including the trailing
+/// zeros, so the literals from `rust_decimal_macros::dec!` round-trip as they are written.
+#[cfg(feature = "decimal")]
+impl XmlValue for rust_decimal::Decimal {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        use std::str::FromStr;
+        rust_decimal::Decimal::from_str(s).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "semver")]
+impl XmlValue for semver::Version {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        semver::Version::parse(s).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(feature = "semver")]
+impl XmlValue for semver::VersionReq {
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        semver::VersionReq::parse(s).map_err(|e| e.to_string())
+    }
+}
+
+/// A URL which must be valid when deserializing, available with the `url` feature.
+#[cfg(feature = "url")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UrlValue(pub url::Url);
+
+#[cfg(feature = "url")]
+impl XmlValue for UrlValue {
+    fn serialize(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        url::Url::parse(s).map(UrlValue).map_err(|e| e.to_string())
+    }
+}
+
+/// A URL kept as it is even if `url::Url` rejects it, like the relative URLs or the
+/// `javascript:` ones. Available with the `url` feature.
+#[cfg(feature = "url")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LenientUrl(pub String);
+
+#[cfg(feature = "url")]
+impl LenientUrl {
+    /// Parses it as an absolute URL.
+    pub fn to_url(&self) -> Result<url::Url, url::ParseError> {
+        url::Url::parse(&self.0)
+    }
+}
+
+#[cfg(feature = "url")]
+impl XmlValue for LenientUrl {
+    fn serialize(&self) -> String {
+        self.0.clone()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        Ok(LenientUrl(s.to_owned()))
+    }
+}
+
+/// An absolute URL or a relative reference like `../img/a.png`, kept as it is. Unlike
+/// `LenientUrl`, deserializing fails if it is not a URI reference of RFC 3986, like one with
+/// whitespaces, control characters, a malformed percent-encoding or an empty scheme, or if it
+/// cannot be resolved against a base URL. The non-ASCII characters of IRIs are allowed.
+/// Available with the `url` feature.
+#[cfg(feature = "url")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelativeUrl(pub String);
+
+#[cfg(feature = "url")]
+impl RelativeUrl {
+    /// Resolves it against `base`.
+    pub fn resolve(&self, base: &url::Url) -> Result<url::Url, url::ParseError> {
+        base.join(&self.0)
+    }
+}
+
+#[cfg(feature = "url")]
+impl XmlValue for RelativeUrl {
+    fn serialize(&self) -> String {
+        self.0.clone()
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        check_uri_reference(s).map_err(|e| format!("{} is not a URL reference: {}", s, e))?;
+        let base = url::Url::parse("http://base.invalid/").map_err(|e| e.to_string())?;
+        match base.join(s) {
+            | Ok(_) => Ok(RelativeUrl(s.to_owned())),
+            | Err(e) => Err(format!("{} is not a URL reference: {}", s, e)),
+        }
+    }
+}
+
+/// Checks the characters and the scheme of a URI reference against RFC 3986.
+#[cfg(feature = "url")]
+fn check_uri_reference(s: &str) -> Result<(), String> {
+    // A `:` before any `/`, `?` or `#` ends the scheme, which a relative path cannot contain
+    let head = s.split(['/', '?', '#']).next().unwrap_or_default();
+    if let Some((scheme, _)) = head.split_once(':') {
+        let mut chars = scheme.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            || !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            return Err(format!("invalid scheme {:?}", scheme));
+        }
+    }
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            | '%' => {
+                let hex = chars
+                    .by_ref()
+                    .take(2)
+                    .filter(char::is_ascii_hexdigit)
+                    .count();
+                if hex != 2 {
+                    return Err("malformed percent-encoding".to_string());
+                }
+            },
+            | c if c.is_ascii_alphanumeric() => {},
+            | '-' | '.' | '_' | '~' | ':' | '/' | '?' | '#' | '[' | ']' | '@' => {},
+            | '!' | '$' | '&' | '\'' | '(' | ')' | '*' | '+' | ',' | ';' | '=' => {},
+            | c if !c.is_ascii() && !c.is_whitespace() && !c.is_control() => {},
+            | c => return Err(format!("invalid character {:?}", c)),
+        }
+    }
+    Ok(())
+}
+
+/// Binary data encoded as base64 with the standard alphabet, like the embedded images or the
+/// signature values. Available with the `base64` feature.
+#[cfg(feature = "base64")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Base64Encoded(pub Vec<u8>);
+
+#[cfg(feature = "base64")]
+impl XmlValue for Base64Encoded {
+    fn serialize(&self) -> String {
+        encode_base64(&self.0)
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        decode_base64(s).map(Base64Encoded)
+    }
+}
+
+/// Encodes `bytes` as base64 with the standard alphabet. Available with the `base64` feature.
+#[cfg(feature = "base64")]
+pub fn encode_base64(bytes: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Decodes base64 with the standard alphabet, ignoring the surrounding whitespace. Available
+/// with the `base64` feature.
+#[cfg(feature = "base64")]
+pub fn decode_base64(s: &str) -> Result<Vec<u8>, String> {
+    use base64::Engine;
+    base64::engine::general_purpose::STANDARD
+        .decode(s.trim())
+        .map_err(|e| e.to_string())
+}
+
+/// Written like the ISO 8601 durations in seconds, e.g. `PT90S`, `PT1.5S` or `-PT0.25S`.
+/// Available with the `time` feature.
+#[cfg(feature = "time")]
+impl XmlValue for time::Duration {
+    fn serialize(&self) -> String {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let secs = self.whole_seconds().unsigned_abs();
+        let nanos = self.subsec_nanoseconds().unsigned_abs();
+        if nanos == 0 {
+            return format!("{}PT{}S", sign, secs);
+        }
+        let frac = format!("{:09}", nanos);
+        format!("{}PT{}.{}S", sign, secs, frac.trim_end_matches('0'))
+    }
+
+    fn deserialize(s: &str) -> Result<Self, String> {
+        let err = || format!("Cannot parse {} into a duration", s);
+        let (negative, rest) = match s.strip_prefix('-') {
+            | Some(rest) => (true, rest),
+            | None => (false, s),
+        };
+        let rest = rest
+            .strip_prefix("PT")
+            .and_then(|r| r.strip_suffix('S'))
+            .ok_or_else(err)?;
+        let (secs, frac) = match rest.split_once('.') {
+            | Some((secs, frac)) => (secs, frac),
+            | None => (rest, ""),
+        };
+        let all_digits = |p: &str| p.bytes().all(|b| b.is_ascii_digit());
+        if secs.is_empty() || !all_digits(secs) || !all_digits(frac) || frac.len() > 9 {
+            return Err(err());
+        }
+        if rest.contains('.') && frac.is_empty() {
+            return Err(err());
+        }
+        let secs = secs.parse::<i64>().map_err(|_| err())?;
+        let nanos = format!("{:0<9}", frac).parse::<i32>().map_err(|_| err())?;
+        let duration = time::Duration::new(secs, nanos);
+        Ok(if negative { -duration } else { duration })
+    }
+}
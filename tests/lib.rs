@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod tests {
 
-    use xmlserde::{xml_deserialize_from_str, xml_serialize, Unparsed, XmlValue};
+    use xmlserde::{xml_deserialize_from_str, xml_serialize, Unparsed, XmlError, XmlValue};
     use xmlserde_derives::{XmlDeserialize, XmlSerdeEnum, XmlSerialize};
 
     #[test]
@@ -53,9 +53,9 @@ mod tests {
                 reader: &mut xmlserde::quick_xml::Reader<B>,
                 attrs: xmlserde::quick_xml::events::attributes::Attributes,
                 is_empty: bool,
-            ) -> Self {
-                let inner = InnerProperties::deserialize(tag, reader, attrs, is_empty);
-                Self(inner.properties)
+            ) -> Result<Self, xmlserde::XmlError> {
+                let inner = InnerProperties::deserialize(tag, reader, attrs, is_empty)?;
+                Ok(Self(inner.properties))
             }
         }
 
@@ -530,6 +530,7 @@ mod tests {
             #[xmlserde(name = b"aAttr", ty = "attr")]
             pub a_attr1: u32,
         }
+        #[allow(dead_code)]
         #[derive(Debug, XmlSerialize, XmlDeserialize)]
         pub struct Bstruct {
             #[xmlserde(name = b"bAttr", ty = "attr")]
@@ -567,6 +568,7 @@ mod tests {
 
     #[test]
     fn test_generics() {
+        #[allow(dead_code)]
         #[derive(Debug, XmlSerialize, XmlDeserialize)]
         #[xmlserde(root = b"Root")]
         pub struct Root<T: xmlserde::XmlSerialize + xmlserde::XmlDeserialize> {
@@ -574,6 +576,7 @@ mod tests {
             pub dummy: Option<T>,
         }
 
+        #[allow(dead_code)]
         #[derive(XmlSerialize)]
         pub enum EnumB<T: xmlserde::XmlSerialize> {
             #[xmlserde(name = b"a")]
@@ -581,6 +584,7 @@ mod tests {
             A1(T),
         }
 
+        #[allow(dead_code)]
         #[derive(Debug, XmlSerialize)]
         #[xmlserde(root = b"ttt")]
         pub struct AStruct {
@@ -887,6 +891,7 @@ mod tests {
 
     #[test]
     fn test_issue_60() {
+        #[allow(dead_code)]
         #[derive(Clone, Debug, Default, XmlDeserialize)]
         pub struct Parameters {
             #[xmlserde(name = b"parameter", ty = "child")]
@@ -895,12 +900,14 @@ mod tests {
             _instance_parameter: Option<A>,
         }
 
+        #[allow(dead_code)]
         #[derive(Clone, Debug, Default, XmlDeserialize)]
         pub struct A {}
     }
 
     #[test]
     fn test_vec_deserialize() {
+        #[allow(dead_code)]
         #[derive(Debug, XmlDeserialize)]
         pub struct CtTextParagraph {
             #[xmlserde(name = b"pPr", ty = "child")]
@@ -909,8 +916,10 @@ mod tests {
             pub _text_runs: Vec<A>,
         }
 
+        #[allow(dead_code)]
         #[derive(Debug, XmlDeserialize, XmlSerialize)]
         pub struct A {}
+        #[allow(dead_code)]
         #[derive(Debug, XmlDeserialize, XmlSerialize)]
         pub struct CtTextParagraphProperties {}
     }
@@ -1515,4 +1524,2822 @@ mod tests {
         let xml = xml_serialize(observation);
         assert_eq!(xml, r#"<BirdObservation Mood="Chirpy" Species="robin"/>"#);
     }
+
+    #[test]
+    fn test_deserialize_into() {
+        use xmlserde::xml_deserialize_into;
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        #[xmlserde(root = b"config")]
+        struct Config {
+            #[xmlserde(name = b"name", ty = "attr")]
+            name: String,
+            #[xmlserde(name = b"port", ty = "attr")]
+            port: u16,
+            #[xmlserde(name = b"debug", ty = "attr", default = "default_debug")]
+            debug: bool,
+            #[xmlserde(name = b"verbose", ty = "sfc")]
+            verbose: bool,
+            #[xmlserde(name = b"host", ty = "child")]
+            hosts: Vec<Host>,
+            #[xmlserde(name = b"timeout", ty = "child")]
+            timeout: Option<Host>,
+        }
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        struct Host {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        fn default_debug() -> bool {
+            false
+        }
+
+        let xml = r#"<config name="app" port="80" debug="1"><verbose/><host>a</host><timeout>3</timeout></config>"#;
+        let mut config = xml_deserialize_from_str::<Config>(xml).unwrap();
+
+        xml_deserialize_into(&mut config, r#"<config port="8080"/>"#).unwrap();
+        assert_eq!(config.name, "app");
+        assert_eq!(config.port, 8080);
+        assert!(config.debug);
+        assert!(config.verbose);
+        assert_eq!(config.hosts.len(), 1);
+        assert_eq!(config.timeout.as_ref().unwrap().value, "3");
+
+        let patch = r#"<config debug="0"><host>b</host><host>c</host></config>"#;
+        xml_deserialize_into(&mut config, patch).unwrap();
+        assert_eq!(config.port, 8080);
+        assert!(!config.debug);
+        assert_eq!(
+            config.hosts,
+            vec![
+                Host {
+                    value: "b".to_string()
+                },
+                Host {
+                    value: "c".to_string()
+                },
+            ]
+        );
+        assert!(xml_deserialize_into(&mut config, "<other/>").is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_deserialize_from_tokio_file() {
+        use xmlserde::xml_deserialize_from_tokio_file;
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"person")]
+        struct Person {
+            #[xmlserde(name = b"age", ty = "attr")]
+            age: u16,
+            #[xmlserde(ty = "text")]
+            name: String,
+        }
+
+        let path = std::env::temp_dir().join("xmlserde_tokio_file_test.xml");
+        std::fs::write(&path, r#"<person age="8">Tom</person>"#).unwrap();
+        let p = xml_deserialize_from_tokio_file::<Person>(&path)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(p.age, 8);
+        assert_eq!(p.name, "Tom");
+
+        let r = xml_deserialize_from_tokio_file::<Person>(&path).await;
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn test_saturating_attr() {
+        use std::num::Saturating;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"counter")]
+        struct Counter {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: Saturating<u8>,
+        }
+
+        let mut c = xml_deserialize_from_str::<Counter>(r#"<counter value="250"/>"#).unwrap();
+        c.value += 10;
+        assert_eq!(c.value, Saturating(255));
+        assert_eq!(xml_serialize(c), r#"<counter value="255"/>"#);
+        assert!(Saturating::<u8>::deserialize("256").is_err());
+    }
+
+    #[test]
+    fn test_range_attr() {
+        use std::ops::{Range, RangeInclusive};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"firewall")]
+        struct Firewall {
+            #[xmlserde(name = b"ports", ty = "attr")]
+            ports: Range<u16>,
+            #[xmlserde(name = b"levels", ty = "attr")]
+            levels: RangeInclusive<i8>,
+        }
+
+        let xml = r#"<firewall ports="8000..8080" levels="-1..=3"/>"#;
+        let f = xml_deserialize_from_str::<Firewall>(xml).unwrap();
+        assert_eq!(f.ports, 8000..8080);
+        assert_eq!(f.levels, -1..=3);
+        assert_eq!(xml_serialize(f), xml);
+
+        assert!(Range::<u16>::deserialize("1..=2").is_err());
+        assert!(Range::<u16>::deserialize("1-2").is_err());
+        assert!(RangeInclusive::<u16>::deserialize("1..2").is_err());
+    }
+
+    #[cfg(all(feature = "smol-str", feature = "compact-str"))]
+    #[test]
+    fn test_compact_strings_attr() {
+        use compact_str::CompactString;
+        use smol_str::SmolStr;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"cell")]
+        struct Cell {
+            #[xmlserde(name = b"r", ty = "attr")]
+            r: SmolStr,
+            #[xmlserde(name = b"t", ty = "attr")]
+            t: CompactString,
+        }
+
+        let xml = r#"<cell r="A1" t="str"/>"#;
+        let c = xml_deserialize_from_str::<Cell>(xml).unwrap();
+        assert_eq!(c.r, "A1");
+        assert_eq!(c.t, "str");
+        assert_eq!(xml_serialize(c), xml);
+    }
+
+    #[test]
+    fn test_trim_text() {
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"person")]
+        struct Person {
+            #[xmlserde(name = b"age", ty = "attr")]
+            age: u16,
+            #[xmlserde(ty = "text", trim = true)]
+            name: String,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"person")]
+        struct RawPerson {
+            #[xmlserde(ty = "text", trim = false)]
+            name: String,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"size")]
+        struct Size {
+            #[xmlserde(ty = "text", trim = true)]
+            value: Option<u32>,
+        }
+
+        let xml = r#"<person age="8">  John Doe
+        </person>"#;
+        let p = xml_deserialize_from_str::<Person>(xml).unwrap();
+        assert_eq!(p.age, 8);
+        assert_eq!(p.name, "John Doe");
+        let p = xml_deserialize_from_str::<RawPerson>(xml).unwrap();
+        assert_eq!(p.name, "  John Doe\n        ");
+        let s = xml_deserialize_from_str::<Size>("<size> 12 </size>").unwrap();
+        assert_eq!(s.value, Some(12));
+    }
+
+    #[test]
+    fn test_xml_visit() {
+        use xmlserde::{xml_visit, XmlVisitor};
+
+        #[derive(Default)]
+        struct Recorder(Vec<String>);
+
+        impl XmlVisitor for Recorder {
+            fn on_attr(&mut self, name: &[u8], value: &str) {
+                let name = String::from_utf8_lossy(name);
+                self.0.push(format!("attr {}={}", name, value));
+            }
+
+            fn on_child_start(&mut self, tag: &[u8]) -> bool {
+                self.0
+                    .push(format!("start {}", String::from_utf8_lossy(tag)));
+                tag != b"skipped"
+            }
+
+            fn on_child_end(&mut self, tag: &[u8]) {
+                self.0.push(format!("end {}", String::from_utf8_lossy(tag)));
+            }
+
+            fn on_text(&mut self, text: &str) {
+                self.0.push(format!("text {}", text));
+            }
+        }
+
+        let xml = r#"<doc><person age="8"><name>Tom &amp; Jerry</name><pet t="cat"/></person><skipped><person/></skipped></doc>"#;
+        let mut recorder = Recorder::default();
+        xml_visit(xml, b"person", &mut recorder).unwrap();
+        assert_eq!(
+            recorder.0,
+            vec![
+                "attr age=8",
+                "start name",
+                "text Tom & Jerry",
+                "end name",
+                "start pet",
+                "attr t=cat",
+                "end pet",
+            ]
+        );
+
+        let mut recorder = Recorder::default();
+        xml_visit(xml, b"doc", &mut recorder).unwrap();
+        assert_eq!(recorder.0.last().unwrap(), "start skipped");
+        assert_eq!(
+            recorder.0.iter().filter(|e| *e == "start person").count(),
+            1
+        );
+
+        let mut recorder = Recorder::default();
+        assert!(xml_visit(xml, b"missing", &mut recorder).is_err());
+        assert!(xml_visit("<doc><a></doc>", b"doc", &mut recorder).is_err());
+    }
+
+    #[test]
+    fn test_xml_builder() {
+        use xmlserde::XmlBuilder;
+
+        let xml = XmlBuilder::element("person")
+            .attr("age", "8")
+            .child("name", |name| {
+                name.text("Tom & Jerry");
+            })
+            .child("pet", |pet| {
+                pet.attr("t", "cat").child("toy", |_| {});
+            })
+            .build();
+        assert_eq!(
+            xml,
+            r#"<person age="8"><name>Tom &amp; Jerry</name><pet t="cat"><toy/></pet></person>"#
+        );
+        assert_eq!(XmlBuilder::element("empty").build(), "<empty/>");
+    }
+
+    #[test]
+    fn test_skip_serializing_if_default() {
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"font")]
+        struct Font {
+            #[xmlserde(name = b"bold", ty = "attr", skip_serializing_if_default)]
+            bold: bool,
+            #[xmlserde(name = b"size", ty = "attr", skip_serializing_if_default)]
+            size: u32,
+            #[xmlserde(name = b"family", ty = "attr", skip_serializing_if_default)]
+            family: String,
+            #[xmlserde(name = b"color", ty = "child", skip_serializing_if_default)]
+            color: Color,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq, Default)]
+        struct Color {
+            #[xmlserde(name = b"rgb", ty = "attr", skip_serializing_if_default)]
+            rgb: String,
+        }
+
+        let font = xml_deserialize_from_str::<Font>("<font/>").unwrap();
+        assert_eq!(
+            font,
+            Font {
+                bold: false,
+                size: 0,
+                family: String::new(),
+                color: Color::default(),
+            }
+        );
+        assert_eq!(xml_serialize(font), "<font/>");
+
+        let xml = r#"<font bold="1" size="12"><color rgb="FF0000"/></font>"#;
+        let font = xml_deserialize_from_str::<Font>(xml).unwrap();
+        assert!(font.bold);
+        assert_eq!(font.family, "");
+        assert_eq!(xml_serialize(font), xml);
+    }
+
+    #[test]
+    fn test_unparsed_preserve_ns() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"ext", ty = "child", preserve_ns)]
+            ext: Unparsed,
+            #[xmlserde(name = b"other", ty = "child", preserve_ns)]
+            others: Vec<Unparsed>,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct PlainRoot {
+            #[xmlserde(name = b"ext", ty = "child")]
+            ext: Unparsed,
+        }
+
+        let xml = r#"<root xmlns="http://d" xmlns:a="http://a"><ext xmlns:a="http://b"><a:foo/></ext><other><a:bar/></other></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(
+            xml_serialize(root),
+            r#"<root><ext xmlns="http://d" xmlns:a="http://b"><a:foo/></ext><other xmlns="http://d" xmlns:a="http://a"><a:bar/></other></root>"#
+        );
+
+        let root = xml_deserialize_from_str::<PlainRoot>(xml).unwrap();
+        assert_eq!(
+            xml_serialize(root),
+            r#"<root><ext xmlns:a="http://b"><a:foo/></ext></root>"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_with_fallback() {
+        use xmlserde::xml_deserialize_from_str_with_fallback;
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        #[xmlserde(root = b"config")]
+        struct Config {
+            #[xmlserde(name = b"port", ty = "attr")]
+            port: u16,
+        }
+
+        let fallback = || Config { port: 80 };
+        let config = xml_deserialize_from_str_with_fallback(r#"<config port="8080"/>"#, fallback);
+        assert_eq!(config.port, 8080);
+        let config = xml_deserialize_from_str_with_fallback("<other/>", fallback);
+        assert_eq!(config.port, 80);
+        let config = xml_deserialize_from_str_with_fallback("", fallback);
+        assert_eq!(config.port, 80);
+    }
+
+    #[test]
+    fn test_result_child() {
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        struct Item {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: u32,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"good", ty = "child")]
+            good: Result<Item, ()>,
+            #[xmlserde(name = b"bad", ty = "child")]
+            bad: Result<Item, String>,
+            #[xmlserde(name = b"missing", ty = "child")]
+            missing: Result<Item, ()>,
+            #[xmlserde(name = b"after", ty = "attr")]
+            after: u32,
+        }
+
+        let xml = r#"<root after="3"><good value="1"/><bad value="x"><nested/></bad></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.good, Ok(Item { value: 1 }));
+        assert_eq!(root.bad, Err(String::new()));
+        assert_eq!(root.missing, Err(()));
+        assert_eq!(root.after, 3);
+        assert_eq!(
+            xml_serialize(root),
+            r#"<root after="3"><good value="1"/></root>"#
+        );
+    }
+
+    #[test]
+    fn test_boxed_attr() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: Box<u64>,
+            #[xmlserde(name = b"count", ty = "attr")]
+            count: Option<Box<u32>>,
+        }
+
+        let xml = r#"<root id="1" count="2"/>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(*root.id, 1);
+        assert_eq!(root.count, Some(Box::new(2)));
+        assert_eq!(xml_serialize(root), xml);
+    }
+
+    #[test]
+    fn test_serialize_with_doctype() {
+        use xmlserde::xml_serialize_with_doctype;
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"html")]
+        struct Html {
+            #[xmlserde(name = b"lang", ty = "attr")]
+            lang: String,
+        }
+
+        let html = Html {
+            lang: String::from("en"),
+        };
+        assert_eq!(
+            xml_serialize_with_doctype(html, "html"),
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><!DOCTYPE html><html lang="en"/>"#
+        );
+    }
+
+    #[test]
+    fn test_required_option() {
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"id", ty = "attr", required)]
+            id: Option<u32>,
+        }
+
+        let root = xml_deserialize_from_str::<Root>(r#"<root id="1"/>"#).unwrap();
+        assert_eq!(root.id, Some(1));
+        let err = xml_deserialize_from_str::<Root>("<root/>").err().unwrap();
+        assert!(matches!(
+            err.inner,
+            XmlError::MissingRequiredField {
+                element: "Root",
+                field: "id"
+            }
+        ));
+    }
+
+    #[test]
+    fn test_linked_list_child() {
+        use std::collections::LinkedList;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        struct Task {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"queue")]
+        struct Queue {
+            #[xmlserde(name = b"task", ty = "child")]
+            tasks: LinkedList<Task>,
+        }
+
+        let xml = r#"<queue><task id="1"/><task id="2"/></queue>"#;
+        let queue = xml_deserialize_from_str::<Queue>(xml).unwrap();
+        assert_eq!(queue.tasks.iter().map(|t| t.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(xml_serialize(queue), xml);
+    }
+
+    #[test]
+    fn test_deserialize_from_owned_string() {
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        let xml = String::from(r#"<root id="1"/>"#);
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.id, 1);
+    }
+
+    #[test]
+    fn test_serialize_reference() {
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        let root = Root { id: 1 };
+        assert_eq!(xml_serialize(&root), r#"<root id="1"/>"#);
+        assert_eq!(xml_serialize(root), r#"<root id="1"/>"#);
+    }
+
+    #[test]
+    fn test_path_buf_attr() {
+        use std::path::PathBuf;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"Compile")]
+        struct Compile {
+            #[xmlserde(name = b"Include", ty = "attr")]
+            include: PathBuf,
+        }
+
+        let xml = r#"<Compile Include="src/main.cs"/>"#;
+        let compile = xml_deserialize_from_str::<Compile>(xml).unwrap();
+        assert_eq!(compile.include, PathBuf::from("src").join("main.cs"));
+        assert_eq!(xml_serialize(compile), xml);
+    }
+
+    #[test]
+    fn test_char_attr() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"csv")]
+        struct Csv {
+            #[xmlserde(name = b"separator", ty = "attr")]
+            separator: char,
+        }
+
+        let xml = r#"<csv separator=";"/>"#;
+        let csv = xml_deserialize_from_str::<Csv>(xml).unwrap();
+        assert_eq!(csv.separator, ';');
+        assert_eq!(xml_serialize(csv), xml);
+
+        assert!(<char as XmlValue>::deserialize("").is_err());
+        assert!(<char as XmlValue>::deserialize("ab").is_err());
+        assert_eq!(<char as XmlValue>::deserialize("é"), Ok('é'));
+    }
+
+    #[test]
+    fn test_system_time_attr() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+        use xmlserde::Iso8601SystemTime;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"log")]
+        struct Log {
+            #[xmlserde(name = b"created", ty = "attr")]
+            created: SystemTime,
+            #[xmlserde(name = b"updated", ty = "attr")]
+            updated: Iso8601SystemTime,
+        }
+
+        let xml = r#"<log created="1704164645" updated="2024-01-02T03:04:05.5Z"/>"#;
+        let log = xml_deserialize_from_str::<Log>(xml).unwrap();
+        assert_eq!(log.created, UNIX_EPOCH + Duration::from_secs(1704164645));
+        assert_eq!(
+            log.updated.0,
+            UNIX_EPOCH + Duration::from_millis(1704164645500)
+        );
+        assert_eq!(xml_serialize(log), xml);
+
+        let t = <Iso8601SystemTime as XmlValue>::deserialize("2024-01-02T05:04:05+02:00");
+        assert_eq!(t.unwrap().0, UNIX_EPOCH + Duration::from_secs(1704164645));
+        let t = Iso8601SystemTime(UNIX_EPOCH - Duration::from_secs(86400));
+        assert_eq!(t.serialize(), "1969-12-31T00:00:00Z");
+        assert!(<SystemTime as XmlValue>::deserialize("yesterday").is_err());
+
+        // The times out of range are errors instead of overflowing
+        for s in [
+            "99999999999999999-01-01T00:00:00Z",
+            "999999999999-01-01T00:00:00Z",
+            "2024-01-01T00:00:00+99999999999999999:00",
+            "99999999999999999999",
+        ] {
+            assert!(<SystemTime as XmlValue>::deserialize(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_boxed_option_child() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"node")]
+        struct Node {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: u32,
+            #[xmlserde(name = b"node", ty = "child")]
+            next: Box<Option<Node>>,
+        }
+
+        let xml = r#"<node value="1"><node value="2"/></node>"#;
+        let node = xml_deserialize_from_str::<Node>(xml).unwrap();
+        let next = node.next.as_ref().as_ref().unwrap();
+        assert_eq!(next.value, 2);
+        assert!(next.next.is_none());
+        assert_eq!(xml_serialize(node), xml);
+    }
+
+    #[test]
+    fn test_cow_str_attr() {
+        use std::borrow::Cow;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"kind", ty = "attr")]
+            kind: Cow<'static, str>,
+        }
+
+        let root = Root {
+            kind: Cow::Borrowed("static"),
+        };
+        let xml = xml_serialize(root);
+        assert_eq!(xml, r#"<root kind="static"/>"#);
+        let root = xml_deserialize_from_str::<Root>(&xml).unwrap();
+        assert!(matches!(root.kind, Cow::Owned(ref s) if s == "static"));
+    }
+
+    #[test]
+    fn test_unparsed_patch_attr() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"a", ty = "child")]
+            link: Unparsed,
+        }
+
+        let xml = r#"<root><a href="http://example.com">x</a></root>"#;
+        let mut root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert!(root
+            .link
+            .patch_attr("href", |url| url.replace("http://", "https://")));
+        assert!(!root.link.patch_attr("title", |t| t.to_owned()));
+        assert_eq!(
+            xml_serialize(root),
+            r#"<root><a href="https://example.com">x</a></root>"#
+        );
+    }
+
+    #[test]
+    fn test_unparsed_into_events() {
+        use xmlserde::quick_xml::events::Event;
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"a", ty = "child")]
+            a: Unparsed,
+            #[xmlserde(name = b"b", ty = "child")]
+            b: Unparsed,
+        }
+
+        let xml = r#"<root><a k="v"><c/>text</a><b/></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        let mut writer = xmlserde::quick_xml::Writer::new(Vec::new());
+        let events = root.a.into_events(b"a");
+        assert!(matches!(events[0], Event::Start(_)));
+        events
+            .into_iter()
+            .filter(|e| !matches!(e, Event::Text(_)))
+            .for_each(|e| writer.write_event(e).unwrap());
+        root.b
+            .into_events(b"b")
+            .into_iter()
+            .for_each(|e| writer.write_event(e).unwrap());
+        assert_eq!(writer.into_inner(), br#"<a k="v"><c/></a><b/>"#);
+    }
+
+    #[test]
+    fn test_validate_wellformed() {
+        use xmlserde::xml_validate_wellformed;
+
+        assert!(xml_validate_wellformed(r#"<?xml version="1.0"?><a x="1"><b/>t</a>"#).is_ok());
+        assert!(xml_validate_wellformed("<a><b></a>").is_err());
+        assert!(xml_validate_wellformed("<a><b/>").is_err());
+        assert!(xml_validate_wellformed("<a/><b/>").is_err());
+        assert!(xml_validate_wellformed("text").is_err());
+        assert!(xml_validate_wellformed("").is_err());
+        assert!(xml_validate_wellformed(r#"<a b="&lt;">&amp;&#65;</a>"#).is_ok());
+        assert!(xml_validate_wellformed(r#"<a b="1" b="2"/>"#).is_err());
+        assert!(xml_validate_wellformed("<a b=1/>").is_err());
+        assert!(xml_validate_wellformed("<a>&bogus;</a>").is_err());
+        assert!(xml_validate_wellformed(r#"<a b="&bogus;"/>"#).is_err());
+        assert!(xml_validate_wellformed("<!-- a - b --><r/>").is_ok());
+        assert!(xml_validate_wellformed("<!-- a -- b --><r/>").is_err());
+        assert!(xml_validate_wellformed("<r><!-- a ---></r>").is_err());
+    }
+
+    #[test]
+    fn test_unit_enum_child() {
+        #[derive(Debug, PartialEq, XmlSerialize, XmlDeserialize)]
+        enum Color {
+            #[xmlserde(name = b"red")]
+            Red,
+            #[xmlserde(name = b"green")]
+            Green,
+        }
+
+        #[derive(XmlSerialize, XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"color", ty = "child")]
+            color: Color,
+            #[xmlserde(name = b"size", ty = "child")]
+            sizes: Vec<Unparsed>,
+        }
+
+        let xml = r#"<root><color><green>x<size/></green><size/></color><size/></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.color, Color::Green);
+        assert_eq!(root.sizes.len(), 1);
+        assert_eq!(
+            xml_serialize(root),
+            "<root><color><green/></color><size/></root>"
+        );
+    }
+
+    #[test]
+    fn test_merge_documents() {
+        use xmlserde::xml_merge_documents;
+
+        let base = r#"<?xml version="1.0"?><config port="80" host="a"><db name="x"/></config>"#;
+        let overlay = r#"<config port="8080" debug="1"><cache/></config>"#;
+        assert_eq!(
+            xml_merge_documents(base, overlay).unwrap(),
+            r#"<config port="8080" host="a" debug="1"><db name="x"/><cache/></config>"#
+        );
+        assert_eq!(xml_merge_documents("<a/>", "<a/>").unwrap(), "<a/>");
+        assert!(xml_merge_documents("<a/>", "").is_err());
+    }
+
+    #[test]
+    fn test_xml_diff() {
+        use xmlserde::{xml_diff, XmlDiffItem};
+
+        let a =
+            r#"<config port="80" host="a"><db name="x"/><db name="y"/><log>info</log></config>"#;
+        let b = r#"<config host="a" debug="1">
+            <db name="x"/>
+            <log> debug </log>
+            <cache/>
+        </config>"#;
+        assert_eq!(
+            xml_diff(a, b),
+            vec![
+                XmlDiffItem::AttributeChanged {
+                    path: String::from("/config/@port"),
+                    old: String::from("80"),
+                    new: String::new(),
+                },
+                XmlDiffItem::AttributeChanged {
+                    path: String::from("/config/@debug"),
+                    old: String::new(),
+                    new: String::from("1"),
+                },
+                XmlDiffItem::ElementRemoved {
+                    path: String::from("/config/db[2]"),
+                },
+                XmlDiffItem::TextChanged {
+                    path: String::from("/config/log"),
+                    old: String::from("info"),
+                    new: String::from("debug"),
+                },
+                XmlDiffItem::ElementAdded {
+                    path: String::from("/config/cache"),
+                },
+            ]
+        );
+        assert!(xml_diff(a, a).is_empty());
+    }
+
+    #[test]
+    fn test_fixed_decimal_attr() {
+        use xmlserde::FixedDecimal;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"Amt")]
+        struct Amount {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: FixedDecimal,
+        }
+
+        let xml = r#"<Amt value="-1234.500"/>"#;
+        let amount = xml_deserialize_from_str::<Amount>(xml).unwrap();
+        assert_eq!(amount.value, FixedDecimal(String::from("-1234.500")));
+        assert_eq!(xml_serialize(amount), xml);
+
+        for s in ["", "-", "1.", ".5", "1e5", "+1", "1.2.3"] {
+            assert!(<FixedDecimal as XmlValue>::deserialize(s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_reader_config() {
+        use xmlserde::{
+            xml_deserialize_from_str_with_options, ReaderConfig, XmlDeserializeOptions,
+        };
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(ty = "text")]
+            text: String,
+        }
+
+        let xml = "<root>  hello  </root>";
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.text, "  hello  ");
+        let opts = XmlDeserializeOptions {
+            reader: ReaderConfig {
+                trim_text: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let root = xml_deserialize_from_str_with_options::<Root>(xml, &opts).unwrap();
+        assert_eq!(root.text, "hello");
+    }
+
+    #[test]
+    fn test_deny_unknown_attrs_and_children() {
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"pet", deny_unknown_attrs)]
+        struct StrictAttrs {
+            #[xmlserde(ty = "attr", name = b"name")]
+            name: String,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"pet", deny_unknown_children)]
+        struct StrictChildren {
+            #[xmlserde(ty = "attr", name = b"name")]
+            name: String,
+        }
+
+        let unknown_attr = r#"<pet name="Tom" age="1"/>"#;
+        let unknown_child = r#"<pet name="Tom"><ext/></pet>"#;
+        let pet = xml_deserialize_from_str::<StrictAttrs>(unknown_child).unwrap();
+        assert_eq!(pet.name, "Tom");
+        let pet = xml_deserialize_from_str::<StrictChildren>(unknown_attr).unwrap();
+        assert_eq!(pet.name, "Tom");
+        assert!(xml_deserialize_from_str::<StrictAttrs>(unknown_attr).is_err());
+        assert!(xml_deserialize_from_str::<StrictChildren>(unknown_child).is_err());
+    }
+
+    #[test]
+    fn test_vec_limit() {
+        #[derive(XmlDeserialize)]
+        struct Item {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"item", ty = "child", limit = 2)]
+            items: Vec<Item>,
+            #[xmlserde(name = b"last", ty = "child")]
+            last: Item,
+        }
+
+        let xml = r#"<root><item id="1"/><item id="2"><last id="0"/></item><item id="3"><last id="0"/></item><last id="4"/></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.items.iter().map(|i| i.id).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(root.last.id, 4);
+    }
+
+    #[test]
+    fn test_map_child() {
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        struct Property {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: String,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"properties", ty = "child")]
+            properties: BTreeMap<String, Property>,
+            #[xmlserde(name = b"others", ty = "child")]
+            others: HashMap<String, Property>,
+        }
+
+        let xml = r#"<root><properties><size value="12"/><color value="red"></color></properties><others><a value="1"/></others></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.properties["color"].value, "red");
+        assert_eq!(root.properties["size"].value, "12");
+        assert_eq!(root.others["a"].value, "1");
+        assert_eq!(
+            xml_serialize(root),
+            r#"<root><properties><color value="red"/><size value="12"/></properties><others><a value="1"/></others></root>"#
+        );
+    }
+
+    #[test]
+    fn test_os_string_attr() {
+        use std::ffi::OsString;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"file")]
+        struct File {
+            #[xmlserde(name = b"path", ty = "attr")]
+            path: OsString,
+        }
+
+        let xml = r#"<file path="/tmp/a.txt"/>"#;
+        let file = xml_deserialize_from_str::<File>(xml).unwrap();
+        assert_eq!(file.path, OsString::from("/tmp/a.txt"));
+        assert_eq!(xml_serialize(file), xml);
+    }
+
+    #[test]
+    fn test_xml_serde_enum_strip_prefix() {
+        #[derive(Debug, PartialEq, XmlSerdeEnum)]
+        #[xmlserde(strip_prefix = "ST_")]
+        #[allow(non_camel_case_types)]
+        enum HexColor {
+            ST_Auto,
+            #[xmlserde(rename = "ST_red")]
+            Red,
+        }
+
+        assert_eq!(HexColor::ST_Auto.serialize(), "Auto");
+        assert_eq!(HexColor::Red.serialize(), "red");
+        assert_eq!(HexColor::deserialize("Auto"), Ok(HexColor::ST_Auto));
+        assert_eq!(HexColor::deserialize("ST_red"), Ok(HexColor::Red));
+    }
+
+    #[test]
+    fn test_flatten_into() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        struct Book {
+            #[xmlserde(name = b"title", ty = "attr")]
+            title: String,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"library")]
+        struct Library {
+            #[xmlserde(name = b"book", ty = "child", flatten_into = b"books")]
+            books: Vec<Book>,
+            #[xmlserde(name = b"owner", ty = "attr")]
+            owner: String,
+        }
+
+        let xml = r#"<library owner="me"><books><book title="a"/><magazine/><book title="b"></book></books></library>"#;
+        let library = xml_deserialize_from_str::<Library>(xml).unwrap();
+        assert_eq!(library.books.len(), 2);
+        assert_eq!(library.books[1].title, "b");
+        assert_eq!(
+            xml_serialize(library),
+            r#"<library owner="me"><books><book title="a"/><book title="b"/></books></library>"#
+        );
+    }
+
+    #[test]
+    fn test_xml_serialize_all() {
+        use xmlserde::xml_serialize_all;
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"item")]
+        struct Item {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        let items = (1..=3).map(|id| Item { id });
+        assert_eq!(
+            xml_serialize_all(items),
+            r#"<item id="1"/><item id="2"/><item id="3"/>"#
+        );
+        assert_eq!(xml_serialize_all(std::iter::empty::<Item>()), "");
+    }
+
+    #[test]
+    fn test_separated_values() {
+        use xmlserde::{CommaSeparated, SpaceSeparated};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"text")]
+        struct Text {
+            #[xmlserde(name = b"class", ty = "attr")]
+            class: SpaceSeparated<String>,
+            #[xmlserde(name = b"sizes", ty = "attr")]
+            sizes: CommaSeparated<u32>,
+        }
+
+        let xml = r#"<text class=" bold  red" sizes="12,14 , 16"/>"#;
+        let text = xml_deserialize_from_str::<Text>(xml).unwrap();
+        assert_eq!(text.class.0, vec!["bold".to_string(), "red".to_string()]);
+        assert_eq!(text.sizes, CommaSeparated(vec![12, 14, 16]));
+        assert_eq!(
+            xml_serialize(text),
+            r#"<text class="bold red" sizes="12, 14, 16"/>"#
+        );
+        assert!(CommaSeparated::<u32>::deserialize("1,a").is_err());
+    }
+
+    #[test]
+    fn test_hash_map_grouped_children() {
+        use std::collections::HashMap;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        struct Entry {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"groups", ty = "child")]
+            groups: HashMap<String, Vec<Entry>>,
+        }
+
+        let xml = r#"<root><groups><a id="1"/><b id="2"/><a id="3"/></groups></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.groups.len(), 2);
+        let a = root.groups["a"].iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(a, vec![1, 3]);
+        assert_eq!(root.groups["b"][0].id, 2);
+    }
+
+    #[test]
+    fn test_xml_fragment_roundtrip() {
+        use xmlserde::xml_fragment_roundtrip;
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Point {
+            #[xmlserde(name = b"x", ty = "attr")]
+            x: i32,
+            #[xmlserde(name = b"label", ty = "child")]
+            label: Option<Label>,
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Label {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        let point = Point {
+            x: -3,
+            label: Some(Label {
+                value: String::from("origin"),
+            }),
+        };
+        assert!(xml_fragment_roundtrip(point, b"point"));
+    }
+
+    #[test]
+    fn test_tuple_values() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"node")]
+        struct Node {
+            #[xmlserde(name = b"translate", ty = "attr")]
+            translate: (f64, f64, f64),
+            #[xmlserde(name = b"size", ty = "attr")]
+            size: (u32, u32),
+        }
+
+        let xml = r#"<node translate="1 -2.5 0" size="640  480"/>"#;
+        let node = xml_deserialize_from_str::<Node>(xml).unwrap();
+        assert_eq!(node.translate, (1.0, -2.5, 0.0));
+        assert_eq!(node.size, (640, 480));
+        assert_eq!(
+            xml_serialize(node),
+            r#"<node translate="1 -2.5 0" size="640 480"/>"#
+        );
+        assert!(<(u8, u8, u8)>::deserialize("1 2").is_err());
+    }
+
+    #[test]
+    fn test_attr_error_mode() {
+        fn fallback_width() -> u32 {
+            100
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"col")]
+        struct Col {
+            #[xmlserde(name = b"min", ty = "attr", error_mode = "ignore")]
+            min: Option<u32>,
+            #[xmlserde(name = b"max", ty = "attr", error_mode = "default")]
+            max: u32,
+            #[xmlserde(
+                name = b"width",
+                ty = "attr",
+                error_mode = "default",
+                default = "fallback_width"
+            )]
+            width: u32,
+        }
+
+        let xml = r#"<col min="a" max="b" width="c"/>"#;
+        let col = xml_deserialize_from_str::<Col>(xml).unwrap();
+        assert_eq!(col.min, None);
+        assert_eq!(col.max, 0);
+        assert_eq!(col.width, 100);
+    }
+
+    #[test]
+    fn test_btree_set_attr() {
+        use std::collections::BTreeSet;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"div")]
+        struct Div {
+            #[xmlserde(name = b"class", ty = "attr")]
+            class: BTreeSet<String>,
+        }
+
+        let xml = r#"<div class="orange apple banana apple"/>"#;
+        let div = xml_deserialize_from_str::<Div>(xml).unwrap();
+        assert_eq!(div.class.len(), 3);
+        assert_eq!(xml_serialize(div), r#"<div class="apple banana orange"/>"#);
+    }
+
+    #[derive(XmlDeserialize)]
+    struct CardinalityItem {
+        #[xmlserde(name = b"v", ty = "attr")]
+        v: u32,
+    }
+
+    #[derive(XmlDeserialize)]
+    #[xmlserde(root = b"root")]
+    struct CardinalityRoot {
+        #[xmlserde(name = b"item", ty = "child", at_most = 1, at_least = 1)]
+        item: CardinalityItem,
+    }
+
+    #[test]
+    fn test_child_cardinality() {
+        let root = xml_deserialize_from_str::<CardinalityRoot>(r#"<root><item v="1"/></root>"#);
+        assert_eq!(root.unwrap().item.v, 1);
+    }
+
+    #[test]
+    fn test_child_cardinality_exceeded() {
+        let err = xml_deserialize_from_str::<CardinalityRoot>(
+            r#"<root><item v="1"/><item v="2"/></root>"#,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err.inner.to_string(),
+            "expected at most 1 'item' element(s), found 2"
+        );
+    }
+
+    #[test]
+    fn test_xml_serialize_cow() {
+        use std::borrow::Cow;
+        use xmlserde::xml_serialize_cow;
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"ping")]
+        struct Ping {}
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"pong")]
+        struct Pong {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u8,
+        }
+
+        let ping = xml_serialize_cow(Ping {});
+        assert!(matches!(ping, Cow::Borrowed("<ping/>")));
+        assert_eq!(ping, xml_serialize(Ping {}));
+        let pong = xml_serialize_cow(Pong { id: 1 });
+        assert!(matches!(pong, Cow::Owned(_)));
+        assert_eq!(pong, r#"<pong id="1"/>"#);
+    }
+
+    #[test]
+    fn test_btree_map_property_bag() {
+        use std::collections::BTreeMap;
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"item")]
+        struct Item {
+            #[xmlserde(name = b"properties", ty = "child")]
+            properties: BTreeMap<String, String>,
+        }
+
+        let xml = r#"<item><properties><color>red</color><size>12</size><empty/><note>a &amp; <b>b</b></note></properties></item>"#;
+        let item = xml_deserialize_from_str::<Item>(xml).unwrap();
+        assert_eq!(item.properties.len(), 4);
+        assert_eq!(item.properties["color"], "red");
+        assert_eq!(item.properties["size"], "12");
+        assert_eq!(item.properties["empty"], "");
+        assert_eq!(item.properties["note"], "a & ");
+
+        let xml = r#"<item><properties><a>x&bogus;y</a></properties></item>"#;
+        assert!(xml_deserialize_from_str::<Item>(xml).is_err());
+    }
+
+    #[test]
+    fn test_option_xml_value() {
+        assert_eq!(<Option<u32>>::deserialize(""), Ok(None));
+        assert_eq!(<Option<u32>>::deserialize("12"), Ok(Some(12)));
+        assert!(<Option<u32>>::deserialize("a").is_err());
+        assert_eq!(Some(3u8).serialize(), "3");
+        assert_eq!(Option::<u8>::None.serialize(), "");
+    }
+
+    #[test]
+    fn test_xml_deserialize_from_str_lenient() {
+        use xmlserde::xml_deserialize_from_str_lenient;
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"pet")]
+        #[xmlserde(deny_unknown_fields)]
+        struct Pet {
+            #[xmlserde(ty = "attr", name = b"name")]
+            name: String,
+        }
+
+        let xml = r#"<pet name="Chaplin" age="1"><weight/></pet>"#;
+        let pet = xml_deserialize_from_str_lenient::<Pet>(xml).unwrap();
+        assert_eq!(pet.name, "Chaplin");
+        assert!(xml_deserialize_from_str::<Pet>(xml).is_err());
+    }
+
+    #[test]
+    fn test_reverse_attr() {
+        use std::cmp::Reverse;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"task")]
+        struct Task {
+            #[xmlserde(name = b"priority", ty = "attr")]
+            priority: Reverse<u32>,
+        }
+
+        let task = xml_deserialize_from_str::<Task>(r#"<task priority="3"/>"#).unwrap();
+        assert_eq!(task.priority, Reverse(3));
+        assert_eq!(xml_serialize(task), r#"<task priority="3"/>"#);
+    }
+
+    #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+    struct CoordPoint {
+        #[xmlserde(name = b"x", ty = "attr")]
+        x: i32,
+    }
+
+    #[derive(Debug, XmlDeserialize, XmlSerialize)]
+    #[xmlserde(root = b"rect")]
+    struct Rect {
+        #[xmlserde(name = b"pt", ty = "child")]
+        points: [CoordPoint; 2],
+    }
+
+    #[test]
+    fn test_array_children() {
+        let xml = r#"<rect><pt x="1"/><pt x="2"/></rect>"#;
+        let rect = xml_deserialize_from_str::<Rect>(xml).unwrap();
+        assert_eq!(rect.points, [CoordPoint { x: 1 }, CoordPoint { x: 2 }]);
+        assert_eq!(xml_serialize(rect), xml);
+    }
+
+    #[test]
+    fn test_array_children_wrong_count() {
+        let xml = r#"<rect><pt x="1"/><pt x="2"/><pt x="3"/></rect>"#;
+        let err = xml_deserialize_from_str::<Rect>(xml).err().unwrap();
+        assert_eq!(err.inner.to_string(), "expected 2 'pt' elements, found 3");
+    }
+
+    #[test]
+    fn test_xml_deserialize_from_reader_with_options() {
+        use xmlserde::{
+            xml_deserialize_from_reader_with_options, ReaderConfig, XmlDeserializeOptions,
+        };
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"note")]
+        struct Note {
+            #[xmlserde(ty = "text")]
+            text: String,
+        }
+
+        let opts = XmlDeserializeOptions {
+            reader: ReaderConfig {
+                trim_text: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let file = std::io::Cursor::new("<note>\n  hi\n</note>");
+        let note = xml_deserialize_from_reader_with_options::<Note, _>(file, &opts).unwrap();
+        assert_eq!(note.text, "hi");
+    }
+
+    #[cfg(feature = "semver")]
+    #[test]
+    fn test_semver_attr() {
+        use semver::{Version, VersionReq};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"dependency")]
+        struct Dependency {
+            #[xmlserde(name = b"version", ty = "attr")]
+            version: Version,
+            #[xmlserde(name = b"requires", ty = "attr")]
+            requires: VersionReq,
+        }
+
+        let xml = r#"<dependency version="1.2.3-beta.1" requires="^1.0.0"/>"#;
+        let dep = xml_deserialize_from_str::<Dependency>(xml).unwrap();
+        assert_eq!(dep.version, Version::parse("1.2.3-beta.1").unwrap());
+        assert!(dep.requires.matches(&Version::new(1, 5, 0)));
+        assert_eq!(xml_serialize(dep), xml);
+        assert!(Version::deserialize("1.2").is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_url_attr() {
+        use xmlserde::{LenientUrl, UrlValue};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"link")]
+        struct Link {
+            #[xmlserde(name = b"href", ty = "attr")]
+            href: UrlValue,
+            #[xmlserde(name = b"alt", ty = "attr")]
+            alt: LenientUrl,
+        }
+
+        let xml = r#"<link href="https://example.com/a?b=1" alt="../img/a.png"/>"#;
+        let link = xml_deserialize_from_str::<Link>(xml).unwrap();
+        assert_eq!(link.href.0.host_str(), Some("example.com"));
+        assert!(link.alt.to_url().is_err());
+        assert_eq!(xml_serialize(link), xml);
+        assert!(UrlValue::deserialize("../img/a.png").is_err());
+    }
+
+    #[test]
+    fn test_derive_default() {
+        fn default_width() -> u32 {
+            80
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize)]
+        #[xmlserde(root = b"page", derive_default)]
+        struct Page {
+            #[xmlserde(name = b"width", ty = "attr", default = "default_width")]
+            width: u32,
+            #[xmlserde(name = b"title", ty = "attr")]
+            title: Option<String>,
+            #[xmlserde(name = b"line", ty = "child")]
+            lines: Vec<Line>,
+            #[xmlserde(name = b"hidden", ty = "sfc")]
+            hidden: bool,
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize)]
+        struct Line {
+            #[xmlserde(ty = "text")]
+            text: String,
+        }
+
+        let page = Page::default();
+        assert_eq!(page.width, 80);
+        assert_eq!(page, xml_deserialize_from_str::<Page>("<page/>").unwrap());
+    }
+
+    #[test]
+    fn test_attr_serialize_with_deserialize_with() {
+        fn to_hex(v: &u32) -> String {
+            format!("{:x}", v)
+        }
+
+        fn from_hex(s: &str) -> Result<u32, String> {
+            u32::from_str_radix(s, 16).map_err(|e| e.to_string())
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"color")]
+        struct Color {
+            #[xmlserde(
+                name = b"rgb",
+                ty = "attr",
+                serialize_with = "to_hex",
+                deserialize_with = "from_hex"
+            )]
+            rgb: u32,
+            #[xmlserde(name = b"alpha", ty = "attr", deserialize_with = "from_hex")]
+            alpha: Option<u32>,
+        }
+
+        let xml = r#"<color rgb="ff00ff" alpha="80"/>"#;
+        let color = xml_deserialize_from_str::<Color>(xml).unwrap();
+        assert_eq!(color.rgb, 0xff00ff);
+        assert_eq!(color.alpha, Some(128));
+        assert_eq!(xml_serialize(color), r#"<color rgb="ff00ff" alpha="128"/>"#);
+    }
+
+    #[test]
+    fn test_duration_seconds_attr() {
+        use std::time::Duration;
+        use xmlserde::DurationSeconds;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"clip")]
+        struct Clip {
+            #[xmlserde(name = b"dur", ty = "attr")]
+            dur: DurationSeconds,
+        }
+
+        let clip = xml_deserialize_from_str::<Clip>(r#"<clip dur="90.5"/>"#).unwrap();
+        assert_eq!(clip.dur.0, Duration::from_millis(90_500));
+        assert_eq!(xml_serialize(clip), r#"<clip dur="90.5"/>"#);
+        assert!(DurationSeconds::deserialize("-1").is_err());
+        assert!(DurationSeconds::deserialize("abc").is_err());
+    }
+
+    #[test]
+    fn test_unparsed_attr_and_child_count() {
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"ext", ty = "child")]
+            ext: Vec<Unparsed>,
+        }
+
+        let xml = r#"<root><ext a="1" b="2"><x><y/></x>text<z/></ext><ext/></root>"#;
+        let root = xml_deserialize_from_str::<Root>(xml).unwrap();
+        assert_eq!(root.ext[0].attr_count(), 2);
+        assert_eq!(root.ext[0].child_count(), 2);
+        assert_eq!(root.ext[1].attr_count(), 0);
+        assert_eq!(root.ext[1].child_count(), 0);
+    }
+
+    #[test]
+    fn test_untagged_enum_vec_with_vec_size() {
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        enum Run {
+            #[xmlserde(name = b"r")]
+            R(Text),
+            #[xmlserde(name = b"br")]
+            Br(Text),
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Text {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"p")]
+        struct Paragraph {
+            #[xmlserde(name = b"cnt", ty = "attr", skip_serializing_if_default)]
+            cnt: usize,
+            #[xmlserde(ty = "untagged_enum", vec_size = "cnt")]
+            runs: Vec<Run>,
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"p")]
+        struct FixedParagraph {
+            #[xmlserde(ty = "untagged_enum", vec_size = 16)]
+            runs: Vec<Run>,
+        }
+
+        let xml = r#"<p cnt="10"><r>a</r><br>b</br></p>"#;
+        let p = xml_deserialize_from_str::<Paragraph>(xml).unwrap();
+        assert_eq!(p.runs.len(), 2);
+        assert!(p.runs.capacity() >= 10);
+        assert_eq!(
+            p.runs[1],
+            Run::Br(Text {
+                value: String::from("b")
+            })
+        );
+        assert_eq!(xml_serialize(p), xml);
+        let p = xml_deserialize_from_str::<FixedParagraph>(xml).unwrap();
+        assert!(p.runs.capacity() >= 16);
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_attr() {
+        use rust_decimal::Decimal;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"amount")]
+        struct Amount {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: Decimal,
+        }
+
+        let xml = r#"<amount value="-1234.50"/>"#;
+        let amount = xml_deserialize_from_str::<Amount>(xml).unwrap();
+        assert_eq!(amount.value, Decimal::new(-123450, 2));
+        assert_eq!(xml_serialize(amount), xml);
+        assert!(<Decimal as XmlValue>::deserialize("1.2.3").is_err());
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn test_relative_url_attr() {
+        use xmlserde::RelativeUrl;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"img")]
+        struct Img {
+            #[xmlserde(name = b"src", ty = "attr")]
+            src: RelativeUrl,
+        }
+
+        let xml = r#"<img src="../img/a.png?x=1"/>"#;
+        let img = xml_deserialize_from_str::<Img>(xml).unwrap();
+        let base = url::Url::parse("https://example.com/docs/page.xml").unwrap();
+        assert_eq!(
+            img.src.resolve(&base).unwrap().as_str(),
+            "https://example.com/img/a.png?x=1"
+        );
+        assert_eq!(xml_serialize(img), xml);
+        assert!(RelativeUrl::deserialize("https://example.com/a").is_ok());
+        assert!(RelativeUrl::deserialize("http://[::1").is_err());
+        assert!(RelativeUrl::deserialize("a%20b/c#top").is_ok());
+        for s in [
+            "not a url",
+            "a\tb",
+            "a\u{7}b",
+            ":foo",
+            "1http://x",
+            "a:b c",
+            "100%",
+            "%zz",
+            "a<b>",
+        ] {
+            assert!(RelativeUrl::deserialize(s).is_err(), "{:?}", s);
+        }
+    }
+
+    #[test]
+    fn test_field_order() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        struct Value {
+            #[xmlserde(ty = "text")]
+            v: String,
+        }
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"person")]
+        struct Person {
+            #[xmlserde(name = b"age", ty = "attr")]
+            age: u8,
+            #[xmlserde(name = b"id", ty = "attr", order = 0)]
+            id: u32,
+            #[xmlserde(name = b"email", ty = "child", order = 2)]
+            email: Value,
+            #[xmlserde(name = b"last", ty = "child", order = 1)]
+            last: Value,
+            #[xmlserde(name = b"first", ty = "child", order = 1)]
+            first: Value,
+        }
+
+        let person = Person {
+            age: 30,
+            id: 7,
+            email: Value { v: "a@b.c".into() },
+            last: Value { v: "Doe".into() },
+            first: Value { v: "John".into() },
+        };
+        assert_eq!(
+            xml_serialize(person),
+            r#"<person id="7" age="30"><last>Doe</last><first>John</first><email>a@b.c</email></person>"#
+        );
+    }
+
+    #[test]
+    fn test_unparsed_from_reader() {
+        use std::io::BufRead;
+        use xmlserde::quick_xml;
+
+        struct Extension {
+            body: Unparsed,
+        }
+
+        impl xmlserde::XmlDeserialize for Extension {
+            fn deserialize<B: BufRead>(
+                tag: &[u8],
+                reader: &mut quick_xml::Reader<B>,
+                attrs: quick_xml::events::attributes::Attributes,
+                is_empty: bool,
+            ) -> Result<Self, xmlserde::XmlError> {
+                Ok(Extension {
+                    body: Unparsed::from_reader(tag, reader, attrs, is_empty)?,
+                })
+            }
+        }
+
+        #[derive(XmlDeserialize)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"ext", ty = "child")]
+            ext: Extension,
+        }
+
+        let root =
+            xml_deserialize_from_str::<Root>(r#"<root><ext v="2"><a/></ext></root>"#).unwrap();
+        assert_eq!(root.ext.body.attr_count(), 1);
+        assert_eq!(root.ext.body.child_count(), 1);
+    }
+
+    #[test]
+    fn test_hex_bytes() {
+        use xmlserde::HexBytes;
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"digest")]
+        struct Digest {
+            #[xmlserde(ty = "text")]
+            value: HexBytes,
+        }
+
+        let xml = "<digest>DEADbeef00</digest>";
+        let digest = xml_deserialize_from_str::<Digest>(xml).unwrap();
+        assert_eq!(digest.value.0, vec![0xde, 0xad, 0xbe, 0xef, 0x00]);
+        assert_eq!(xml_serialize(digest), "<digest>deadbeef00</digest>");
+        assert!(HexBytes::deserialize("abc").is_err());
+        assert!(HexBytes::deserialize("zz").is_err());
+        assert!(HexBytes::deserialize("+1").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_error_position() {
+        #[derive(XmlDeserialize, Debug)]
+        #[xmlserde(root = b"root")]
+        struct Root {
+            #[xmlserde(name = b"a", ty = "attr")]
+            _a: u8,
+        }
+
+        let xml = "<doc><meta></doc><root a=\"1\"/>";
+        let err = xml_deserialize_from_str::<Root>(xml).unwrap_err();
+        assert!(matches!(err.inner, XmlError::ParseError(_)), "{}", err);
+        assert!(err.to_string().ends_with("at byte offset 11"), "{}", err);
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_encoded() {
+        use xmlserde::{decode_base64, encode_base64, Base64Encoded};
+
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"data")]
+        struct Data {
+            #[xmlserde(ty = "text")]
+            value: Base64Encoded,
+        }
+
+        let xml = "<data>aGVsbG8=</data>";
+        let data = xml_deserialize_from_str::<Data>(xml).unwrap();
+        assert_eq!(data.value.0, b"hello");
+        assert_eq!(xml_serialize(data), xml);
+        assert_eq!(encode_base64(&[0xff, 0xfe]), "//4=");
+        assert_eq!(decode_base64(" //4= ").unwrap(), vec![0xff, 0xfe]);
+        assert!(decode_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn test_serialize_with_header() {
+        use xmlserde::xml_serialize_with_header;
+
+        #[derive(XmlSerialize)]
+        #[xmlserde(root = b"config")]
+        struct Config {
+            #[xmlserde(name = b"version", ty = "attr")]
+            version: u8,
+        }
+
+        assert_eq!(
+            xml_serialize_with_header(Config { version: 2 }, "Generated file. Do not edit."),
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><!-- Generated file. Do not edit. --><config version="2"/>"#
+        );
+        // A `--` would end the comment early
+        assert_eq!(
+            xml_serialize_with_header(Config { version: 2 }, "a -- b ---"),
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><!-- a - - b - - - --><config version="2"/>"#
+        );
+    }
+
+    #[test]
+    fn test_enum_with_root() {
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        struct Circle {
+            #[xmlserde(name = b"r", ty = "attr")]
+            r: u32,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        struct Square {
+            #[xmlserde(name = b"side", ty = "attr")]
+            side: u32,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"shape")]
+        enum Shape {
+            #[xmlserde(name = b"circle")]
+            Circle(Circle),
+            #[xmlserde(name = b"square")]
+            Square(Square),
+        }
+
+        let xml = r#"<shape><square side="3"/></shape>"#;
+        let shape = xml_deserialize_from_str::<Shape>(xml).unwrap();
+        assert_eq!(shape, Shape::Square(Square { side: 3 }));
+        assert_eq!(xml_serialize(shape), xml);
+        let shape = xml_deserialize_from_str::<Shape>(r#"<shape><circle r="1"/></shape>"#);
+        assert_eq!(shape.unwrap(), Shape::Circle(Circle { r: 1 }));
+    }
+
+    #[test]
+    fn test_unparsed_try_from_str() {
+        use std::convert::{TryFrom, TryInto};
+        use xmlserde::Unparsed;
+
+        let u: Unparsed = r#"<foo bar="baz"><a/><b/></foo>"#.try_into().unwrap();
+        assert_eq!(u.attr_count(), 1);
+        assert_eq!(u.child_count(), 2);
+        let mut writer = xmlserde::quick_xml::Writer::new(Vec::new());
+        xmlserde::XmlSerialize::serialize(&u, b"foo", &mut writer);
+        let xml = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(xml, r#"<foo bar="baz"><a/><b/></foo>"#);
+        assert!(Unparsed::try_from(String::from("no element")).is_err());
+    }
+
+    #[test]
+    fn test_processing_instruction() {
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        struct Title {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"doc")]
+        struct Doc {
+            #[xmlserde(name = b"xml-stylesheet", ty = "pi")]
+            stylesheet: Option<String>,
+            #[xmlserde(name = b"xml-model", ty = "pi")]
+            model: Option<String>,
+            #[xmlserde(name = b"title", ty = "child")]
+            title: Option<Title>,
+        }
+
+        let xml = r#"<doc><?xml-stylesheet type="text/xsl" href="a.xsl"?><title>T</title></doc>"#;
+        let doc = xml_deserialize_from_str::<Doc>(xml).unwrap();
+        assert_eq!(
+            doc.stylesheet.as_deref(),
+            Some(r#"type="text/xsl" href="a.xsl""#)
+        );
+        assert_eq!(doc.model, None);
+        assert_eq!(doc.title.as_ref().unwrap().value, "T");
+        assert_eq!(xml_serialize(doc), xml);
+
+        let doc = Doc {
+            stylesheet: None,
+            model: Some(String::from(r#"href="a.rng""#)),
+            title: None,
+        };
+        assert_eq!(
+            xml_serialize(doc),
+            r#"<doc><?xml-model href="a.rng"?></doc>"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_collect_stream() {
+        use xmlserde::xml_deserialize_collect_stream;
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        #[xmlserde(root = b"record")]
+        struct Record {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        let xml = r#"<dump><record id="1"/><meta/><page><record id="2"></record></page></dump>"#;
+        let mut records = xml_deserialize_collect_stream::<Record, _>(xml.as_bytes());
+        assert_eq!(records.next().unwrap().unwrap(), Record { id: 1 });
+        assert_eq!(records.next().unwrap().unwrap(), Record { id: 2 });
+        assert!(records.next().is_none());
+
+        let xml = r#"<dump><record id="1"/></page>"#;
+        let records = xml_deserialize_collect_stream::<Record, _>(xml.as_bytes());
+        let results = records.collect::<Vec<_>>();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_duration() {
+        #[derive(XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"timeout")]
+        struct Timeout {
+            #[xmlserde(name = b"value", ty = "attr")]
+            value: time::Duration,
+        }
+
+        let xml = r#"<timeout value="PT1.5S"/>"#;
+        let timeout = xml_deserialize_from_str::<Timeout>(xml).unwrap();
+        assert_eq!(timeout.value, time::Duration::milliseconds(1500));
+        assert_eq!(xml_serialize(timeout), xml);
+        assert_eq!(time::Duration::seconds(90).serialize(), "PT90S");
+        let negative = <time::Duration as XmlValue>::deserialize("-PT0.000000001S").unwrap();
+        assert_eq!(negative, time::Duration::nanoseconds(-1));
+        assert_eq!(negative.serialize(), "-PT0.000000001S");
+        assert!(<time::Duration as XmlValue>::deserialize("PT1.S").is_err());
+        assert!(<time::Duration as XmlValue>::deserialize("P1D").is_err());
+    }
+
+    #[test]
+    fn test_multiple_roots_precedence() {
+        #[derive(XmlDeserialize, Debug)]
+        #[xmlserde(root = [b"primary", b"fallback"])]
+        struct Settings {
+            #[xmlserde(name = b"v", ty = "attr")]
+            v: u8,
+        }
+
+        let xml = r#"<doc><fallback v="1"/><primary v="2"/></doc>"#;
+        assert_eq!(xml_deserialize_from_str::<Settings>(xml).unwrap().v, 2);
+        let xml = r#"<doc><other/><fallback v="1"/></doc>"#;
+        assert_eq!(xml_deserialize_from_str::<Settings>(xml).unwrap().v, 1);
+        let xml = r#"<doc><fallback v="1"/></oops>"#;
+        assert_eq!(xml_deserialize_from_str::<Settings>(xml).unwrap().v, 1);
+        let err = xml_deserialize_from_str::<Settings>("<doc/>").unwrap_err();
+        assert!(
+            matches!(err.inner, XmlError::MissingRoot { expected } if expected == ["primary", "fallback"])
+        );
+    }
+
+    #[test]
+    fn test_tuple_child() {
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"header")]
+        struct Header {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"body")]
+        struct Body {
+            #[xmlserde(ty = "text")]
+            content: String,
+        }
+
+        #[derive(XmlDeserialize, XmlSerialize, Debug, PartialEq)]
+        #[xmlserde(root = b"message")]
+        struct Message {
+            #[xmlserde(name = b"header", ty = "child")]
+            parts: (Header, Body),
+        }
+
+        let xml = r#"<message><header id="7"/><body>hi</body></message>"#;
+        let message = xml_deserialize_from_str::<Message>(xml).unwrap();
+        assert_eq!(
+            message.parts,
+            (
+                Header { id: 7 },
+                Body {
+                    content: String::from("hi")
+                }
+            )
+        );
+        assert_eq!(xml_serialize(message), xml);
+
+        // The second tag is matched exactly against the roots of `Body`
+        for xml in [
+            r#"<message><header id="7"/><BODY>hi</BODY></message>"#,
+            r#"<message><header id="7"/><footer>hi</footer></message>"#,
+        ] {
+            assert!(xml_deserialize_from_str::<Message>(xml).is_err(), "{}", xml);
+        }
+
+        #[derive(XmlDeserialize, Debug)]
+        struct Footer {
+            #[xmlserde(ty = "text")]
+            content: String,
+        }
+
+        #[derive(XmlDeserialize, Debug)]
+        #[xmlserde(root = b"message")]
+        struct Unrooted {
+            #[xmlserde(name = b"header", ty = "child")]
+            parts: (Header, Footer),
+        }
+
+        // Like in serializing, the second type needs a root
+        let xml = r#"<message><header id="7"/><footer>hi</footer></message>"#;
+        assert!(xml_deserialize_from_str::<Unrooted>(xml).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_errors_instead_of_panicking() {
+        #[derive(Debug, XmlDeserialize)]
+        struct Owner {
+            #[xmlserde(name = b"name", ty = "attr")]
+            name: String,
+        }
+
+        #[derive(Debug, XmlDeserialize)]
+        #[xmlserde(root = b"pet")]
+        struct Pet {
+            #[xmlserde(name = b"age", ty = "attr")]
+            age: u8,
+            #[xmlserde(name = b"owner", ty = "child")]
+            owner: Owner,
+        }
+
+        let pet = xml_deserialize_from_str::<Pet>(r#"<pet age="3"><owner name="Tom"/></pet>"#);
+        assert_eq!(pet.unwrap().owner.name, "Tom");
+        let missing = xml_deserialize_from_str::<Pet>(r#"<pet age="3"/>"#).err();
+        assert_eq!(
+            missing.unwrap().inner.to_string(),
+            "missing required field 'owner' in 'Pet'"
+        );
+        let invalid =
+            xml_deserialize_from_str::<Pet>(r#"<pet age="old"><owner name="Tom"/></pet>"#).err();
+        assert_eq!(
+            invalid.unwrap().inner.to_string(),
+            r#"failed to deserialize 'age' from "old""#
+        );
+        let eof = xml_deserialize_from_str::<Pet>(r#"<pet age="3"><owner name="Tom"/>"#).err();
+        assert!(matches!(eof.map(|e| e.inner), Some(XmlError::Eof)));
+    }
+
+    #[test]
+    fn test_xml_error_variants() {
+        #[derive(Debug, XmlDeserialize)]
+        #[xmlserde(root = b"pet", deny_unknown_fields)]
+        struct Pet {
+            #[xmlserde(name = b"age", ty = "attr")]
+            age: u8,
+            #[xmlserde(ty = "text")]
+            name: String,
+        }
+
+        let pet = xml_deserialize_from_str::<Pet>(r#"<pet age="3">Tom</pet>"#).unwrap();
+        assert_eq!((pet.age, pet.name.as_str()), (3, "Tom"));
+        match xml_deserialize_from_str::<Pet>(r#"<pet age="three">Tom</pet>"#)
+            .map_err(XmlError::from)
+        {
+            | Err(XmlError::DeserializeValueFailed { field, raw }) => {
+                assert_eq!((field, raw.as_str()), ("age", "three"))
+            },
+            | r => panic!("unexpected {:?}", r),
+        }
+        match xml_deserialize_from_str::<Pet>(r#"<pet age="3" color="red">Tom</pet>"#)
+            .map_err(XmlError::from)
+        {
+            | Err(XmlError::UnknownField(name)) => assert_eq!(name, "color"),
+            | r => panic!("unexpected {:?}", r),
+        }
+        match xml_deserialize_from_str::<Pet>(r#"<dog age="3">Tom</dog>"#).map_err(XmlError::from) {
+            | Err(XmlError::MissingRoot { expected }) => assert_eq!(expected, ["pet"]),
+            | r => panic!("unexpected {:?}", r),
+        }
+        match xml_deserialize_from_str::<Pet>(r#"<pet age="3">Tom</dog>"#).map_err(XmlError::from) {
+            | Err(XmlError::ParseError(_)) => {},
+            | r => panic!("unexpected {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_error_line_column() {
+        use xmlserde::xml_line_column;
+
+        #[derive(Debug, XmlDeserialize)]
+        #[xmlserde(root = b"config")]
+        struct Config {
+            #[xmlserde(name = b"port", ty = "attr")]
+            _port: u16,
+        }
+
+        let xml = "<doc>\n  <config port=\"x\"/>\n</doc>";
+        let err = xml_deserialize_from_str::<Config>(xml).unwrap_err();
+        assert!(matches!(err.inner, XmlError::DeserializeValueFailed { .. }));
+        assert_eq!(err.line_column(xml.as_bytes()), (2, 21));
+        let xml = "<a>\n  <b></c>\n</a>";
+        let err = xml_deserialize_from_str::<Config>(xml).unwrap_err();
+        assert_eq!(err.line_column(xml.as_bytes()), (2, 6));
+        assert_eq!(xml_line_column("é\nab".as_bytes(), 5), (2, 3));
+    }
+
+    #[test]
+    fn test_skip_deserializing() {
+        fn unknown() -> String {
+            String::from("unknown")
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"report", deny_unknown_fields)]
+        struct Report {
+            #[xmlserde(name = b"count", ty = "attr")]
+            count: u32,
+            #[xmlserde(name = b"total", ty = "attr", skip_deserializing)]
+            total: u32,
+            #[xmlserde(name = b"owner", ty = "attr", skip_deserializing, default = "unknown")]
+            owner: String,
+        }
+
+        let xml = r#"<report count="2" total="10" owner="Tom"/>"#;
+        let report = xml_deserialize_from_str::<Report>(xml).unwrap();
+        assert_eq!(report.count, 2);
+        assert_eq!(report.total, 0);
+        assert_eq!(report.owner, "unknown");
+        let report = Report {
+            total: 7,
+            owner: String::from("Tom"),
+            ..report
+        };
+        assert_eq!(
+            xml_serialize(report),
+            r#"<report count="2" total="7" owner="Tom"/>"#
+        );
+    }
+
+    #[test]
+    fn test_skip() {
+        use std::cell::RefCell;
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"shape", rename_all = "camelCase", deny_unknown_fields)]
+        struct Shape {
+            #[xmlserde(ty = "attr")]
+            side_len: u32,
+            #[xmlserde(skip)]
+            area_cache: RefCell<Option<u32>>,
+        }
+
+        let shape = xml_deserialize_from_str::<Shape>(r#"<shape sideLen="3"/>"#).unwrap();
+        assert_eq!(shape.side_len, 3);
+        assert_eq!(*shape.area_cache.borrow(), None);
+        let err = xml_deserialize_from_str::<Shape>(r#"<shape sideLen="3" areaCache="9"/>"#);
+        assert!(err.is_err());
+        *shape.area_cache.borrow_mut() = Some(9);
+        assert_eq!(xml_serialize(shape), r#"<shape sideLen="3"/>"#);
+    }
+
+    #[test]
+    fn test_text_serialize_with_deserialize_with() {
+        mod ts_ser {
+            pub fn to_str(v: &u64) -> String {
+                format!("@{}", v)
+            }
+
+            pub fn from_str(s: &str) -> Result<u64, String> {
+                let v = s.strip_prefix('@').ok_or("expected '@'")?;
+                v.parse()
+                    .map_err(|e: std::num::ParseIntError| e.to_string())
+            }
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"event")]
+        struct Event {
+            #[xmlserde(
+                ty = "text",
+                serialize_with = "ts_ser::to_str",
+                deserialize_with = "ts_ser::from_str"
+            )]
+            ts: u64,
+        }
+
+        let event = xml_deserialize_from_str::<Event>("<event>@1700000000</event>").unwrap();
+        assert_eq!(event.ts, 1700000000);
+        assert_eq!(xml_serialize(event), "<event>@1700000000</event>");
+        let err = xml_deserialize_from_str::<Event>("<event>1700000000</event>").unwrap_err();
+        assert!(matches!(
+            err.inner,
+            XmlError::DeserializeValueFailed { field: "ts", .. }
+        ));
+    }
+
+    #[test]
+    fn test_flatten() {
+        #[derive(Debug, Default, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Audit {
+            #[xmlserde(name = b"createdBy", ty = "attr")]
+            created_by: String,
+            #[xmlserde(name = b"revision", ty = "attr", default = "default_zero")]
+            revision: u32,
+        }
+
+        fn default_zero() -> u32 {
+            0
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"doc")]
+        struct Doc {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+            #[xmlserde(ty = "flatten")]
+            audit: Audit,
+            #[xmlserde(name = b"title", ty = "child")]
+            title: Title,
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        struct Title {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        let xml = r#"<doc id="1" createdBy="ann" revision="3"><title>T</title></doc>"#;
+        let doc = xml_deserialize_from_str::<Doc>(xml).unwrap();
+        assert_eq!(doc.id, 1);
+        assert_eq!(
+            doc.audit,
+            Audit {
+                created_by: "ann".to_string(),
+                revision: 3,
+            }
+        );
+        assert_eq!(doc.title.value, "T");
+        assert_eq!(xml_serialize(doc), xml);
+
+        let err = xml_deserialize_from_str::<Doc>(r#"<doc id="1"><title>T</title></doc>"#);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_skip_serializing_if() {
+        fn is_zero(v: &u32) -> bool {
+            *v == 0
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        struct Item {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"order")]
+        struct Order {
+            #[xmlserde(name = b"note", ty = "attr", skip_serializing_if = "String::is_empty")]
+            note: String,
+            #[xmlserde(name = b"count", ty = "attr", skip_serializing_if = "is_zero")]
+            count: u32,
+            #[xmlserde(
+                name = b"coupon",
+                ty = "child",
+                skip_serializing_if = "Option::is_none"
+            )]
+            coupon: Option<Item>,
+            #[xmlserde(name = b"item", ty = "child", skip_serializing_if = "Vec::is_empty")]
+            items: Vec<Item>,
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"comment")]
+        struct Comment {
+            #[xmlserde(ty = "text", skip_serializing_if = "String::is_empty")]
+            body: String,
+        }
+
+        let order = Order {
+            note: String::new(),
+            count: 0,
+            coupon: None,
+            items: vec![],
+        };
+        assert_eq!(xml_serialize(order), "<order/>");
+        let order = Order {
+            note: "fast".to_string(),
+            count: 2,
+            coupon: None,
+            items: vec![Item {
+                value: "a".to_string(),
+            }],
+        };
+        let xml = xml_serialize(order);
+        assert_eq!(
+            xml,
+            r#"<order note="fast" count="2"><item>a</item></order>"#
+        );
+        let order = xml_deserialize_from_str::<Order>(&xml).unwrap();
+        assert_eq!(order.count, 2);
+        assert_eq!(order.items.len(), 1);
+
+        let comment = Comment {
+            body: String::new(),
+        };
+        assert_eq!(xml_serialize(comment), "<comment/>");
+        let comment = Comment {
+            body: "hi".to_string(),
+        };
+        assert_eq!(xml_serialize(comment), "<comment>hi</comment>");
+    }
+
+    #[test]
+    fn test_cdata() {
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"script")]
+        struct Script {
+            #[xmlserde(ty = "cdata")]
+            code: String,
+        }
+
+        let script = Script {
+            code: r#"if a < b && c > d { s = "'q'"; }"#.to_string(),
+        };
+        let xml = xml_serialize(script);
+        assert_eq!(
+            xml,
+            r#"<script><![CDATA[if a < b && c > d { s = "'q'"; }]]></script>"#
+        );
+        let script = xml_deserialize_from_str::<Script>(&xml).unwrap();
+        assert_eq!(script.code, r#"if a < b && c > d { s = "'q'"; }"#);
+
+        let script = Script {
+            code: "x[y[0]]>1".to_string(),
+        };
+        let xml = xml_serialize(script);
+        assert_eq!(xml, "<script><![CDATA[x[y[0]]]]><![CDATA[>1]]></script>");
+        let script = xml_deserialize_from_str::<Script>(&xml).unwrap();
+        assert_eq!(script.code, "x[y[0]]>1");
+
+        let script =
+            xml_deserialize_from_str::<Script>("<script>a &lt; &amp;b &quot;&apos;</script>")
+                .unwrap();
+        assert_eq!(script.code, r#"a < &b "'"#);
+    }
+
+    #[test]
+    fn test_attr_map() {
+        use std::collections::HashMap;
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"node")]
+        struct Node {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+            #[xmlserde(ty = "attr_map")]
+            extra: HashMap<String, String>,
+        }
+
+        let node =
+            xml_deserialize_from_str::<Node>(r#"<node id="7" color="red" data-x="1"/>"#).unwrap();
+        assert_eq!(node.id, 7);
+        assert_eq!(node.extra.len(), 2);
+        assert_eq!(node.extra["color"], "red");
+        assert_eq!(node.extra["data-x"], "1");
+
+        let mut extra = HashMap::new();
+        extra.insert("color".to_string(), "blue".to_string());
+        let node = Node { id: 1, extra };
+        assert_eq!(xml_serialize(node), r#"<node id="1" color="blue"/>"#);
+    }
+
+    #[test]
+    fn test_serialize_pretty() {
+        use xmlserde::{
+            xml_serialize_pretty, xml_serialize_pretty_indent, xml_serialize_pretty_with_decl,
+        };
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Book {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+            #[xmlserde(ty = "text")]
+            title: String,
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"shelf")]
+        struct Shelf {
+            #[xmlserde(name = b"book", ty = "child")]
+            books: Vec<Book>,
+        }
+
+        let shelf = Shelf {
+            books: vec![
+                Book {
+                    id: 1,
+                    title: "Dune".to_string(),
+                },
+                Book {
+                    id: 2,
+                    title: "Emma".to_string(),
+                },
+            ],
+        };
+        let xml = xml_serialize_pretty(&shelf);
+        assert_eq!(
+            xml,
+            "<shelf>\n  <book id=\"1\">Dune</book>\n  <book id=\"2\">Emma</book>\n</shelf>"
+        );
+        assert_eq!(xml_deserialize_from_str::<Shelf>(&xml).unwrap(), shelf);
+
+        let xml = xml_serialize_pretty_with_decl(&shelf);
+        assert!(xml
+            .starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<shelf>"));
+        assert_eq!(xml_deserialize_from_str::<Shelf>(&xml).unwrap(), shelf);
+
+        let xml = xml_serialize_pretty_indent(&shelf, b'\t', 1);
+        assert!(xml.contains("\n\t<book id=\"1\">"));
+        assert_eq!(xml_deserialize_from_str::<Shelf>(&xml).unwrap(), shelf);
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        use xmlserde::{
+            xml_serialize_pretty_indent, xml_serialize_pretty_to_writer, xml_serialize_to_writer,
+            xml_serialize_with_decl, xml_serialize_with_decl_to_writer,
+        };
+
+        #[derive(Debug, XmlSerialize)]
+        #[xmlserde(root = b"row")]
+        struct Row {
+            #[xmlserde(name = b"r", ty = "attr")]
+            r: u32,
+            #[xmlserde(name = b"c", ty = "child")]
+            cells: Vec<Cell>,
+        }
+
+        #[derive(Debug, XmlSerialize)]
+        struct Cell {
+            #[xmlserde(ty = "text")]
+            v: String,
+        }
+
+        struct Failing;
+
+        impl std::io::Write for Failing {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk full"))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let row = Row {
+            r: 1,
+            cells: vec![Cell { v: "a".to_string() }, Cell { v: "b".to_string() }],
+        };
+        let mut buf = Vec::new();
+        xml_serialize_to_writer(&row, &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), xml_serialize(&row));
+        let mut buf = Vec::new();
+        xml_serialize_with_decl_to_writer(&row, &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            xml_serialize_with_decl(&row)
+        );
+        let mut buf = Vec::new();
+        xml_serialize_pretty_to_writer(&row, &mut buf, b' ', 4).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            xml_serialize_pretty_indent(&row, b' ', 4)
+        );
+
+        let err = xml_serialize_to_writer(&row, Failing).unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+    }
+
+    #[test]
+    fn test_deserialize_from_bytes_and_file() {
+        use xmlserde::{
+            xml_deserialize_from_bytes, xml_deserialize_from_file, xml_serialize_to_file,
+        };
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"point")]
+        struct Point {
+            #[xmlserde(name = b"x", ty = "attr")]
+            x: i32,
+            #[xmlserde(name = b"y", ty = "attr")]
+            y: i32,
+        }
+
+        let point = xml_deserialize_from_bytes::<Point>(br#"<point x="1" y="-2"/>"#).unwrap();
+        assert_eq!(point, Point { x: 1, y: -2 });
+
+        let path = std::env::temp_dir().join(format!("xmlserde_point_{}.xml", std::process::id()));
+        xml_serialize_to_file(&point, &path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("<?xml"));
+        assert_eq!(xml_deserialize_from_file::<Point, _>(&path).unwrap(), point);
+        std::fs::remove_file(&path).unwrap();
+
+        let err = xml_deserialize_from_file::<Point, _>(&path).unwrap_err();
+        assert!(matches!(err.inner, XmlError::ParseError(_)));
+    }
+
+    #[test]
+    fn test_deserialize_options_limits() {
+        use xmlserde::{xml_deserialize_from_str_with_options, XmlDeserializeOptions};
+
+        #[derive(Debug, XmlDeserialize)]
+        struct Leaf {
+            #[xmlserde(ty = "text")]
+            value: String,
+        }
+
+        #[derive(Debug, XmlDeserialize)]
+        struct Branch {
+            #[xmlserde(name = b"leaf", ty = "child")]
+            leaves: Vec<Leaf>,
+        }
+
+        #[derive(Debug, XmlDeserialize)]
+        #[xmlserde(root = b"tree")]
+        struct Tree {
+            #[xmlserde(name = b"kind", ty = "attr")]
+            kind: Option<String>,
+            #[xmlserde(name = b"branch", ty = "child")]
+            branch: Option<Branch>,
+        }
+
+        let xml = r#"<tree kind="oak"><branch><leaf>a</leaf><leaf>bb</leaf></branch></tree>"#;
+        let opts = XmlDeserializeOptions::default()
+            .trim_text_whitespace(true)
+            .max_depth(3)
+            .max_elements(4)
+            .max_string_len(3);
+        let tree = xml_deserialize_from_str_with_options::<Tree>(xml, &opts).unwrap();
+        assert_eq!(tree.kind.as_deref(), Some("oak"));
+        assert_eq!(tree.branch.unwrap().leaves[1].value, "bb");
+
+        let limit =
+            |opts: &XmlDeserializeOptions| match xml_deserialize_from_str_with_options::<Tree>(
+                xml, opts,
+            )
+            .unwrap_err()
+            .inner
+            {
+                | XmlError::LimitExceeded { limit, max } => (limit, max),
+                | e => panic!("unexpected error: {}", e),
+            };
+        let opts = XmlDeserializeOptions::default().max_depth(2);
+        assert_eq!(limit(&opts), ("max_depth", 2));
+        let opts = XmlDeserializeOptions::default().max_elements(3);
+        assert_eq!(limit(&opts), ("max_elements", 3));
+        let opts = XmlDeserializeOptions::default().max_string_len(1);
+        assert_eq!(limit(&opts), ("max_string_len", 1));
+
+        let opts = XmlDeserializeOptions::default().strict_duplicate_fields(true);
+        let xml = r#"<tree><branch/><branch/></tree>"#;
+        assert!(xml_deserialize_from_str::<Tree>(xml).is_ok());
+        let err = xml_deserialize_from_str_with_options::<Tree>(xml, &opts).unwrap_err();
+        assert!(matches!(err.inner, XmlError::DuplicateField(ref f) if f == "branch"));
+        let xml = r#"<tree kind="a" kind="b"/>"#;
+        let err = xml_deserialize_from_str_with_options::<Tree>(xml, &opts).unwrap_err();
+        assert!(matches!(err.inner, XmlError::DuplicateField(ref f) if f == "kind"));
+    }
+
+    #[test]
+    fn test_net_and_char_xml_values() {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"peer")]
+        struct Peer {
+            #[xmlserde(name = b"ip", ty = "attr")]
+            ip: IpAddr,
+            #[xmlserde(name = b"addr", ty = "attr")]
+            addr: SocketAddr,
+            #[xmlserde(name = b"sep", ty = "attr")]
+            sep: char,
+        }
+
+        let xml = r#"<peer ip="::1" addr="[fe80::1%2]:443" sep=";"/>"#;
+        let peer = xml_deserialize_from_str::<Peer>(xml).unwrap();
+        assert_eq!(peer.ip, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(
+            peer.addr,
+            SocketAddr::V6(SocketAddrV6::new("fe80::1".parse().unwrap(), 443, 0, 2))
+        );
+        assert_eq!(peer.sep, ';');
+        assert_eq!(xml_serialize(peer), xml);
+
+        for (s, ip) in [
+            ("127.0.0.1", IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            ("0.0.0.0", IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            ("::1", IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            (
+                "::ffff:192.0.2.1",
+                IpAddr::V6(Ipv4Addr::new(192, 0, 2, 1).to_ipv6_mapped()),
+            ),
+        ] {
+            assert_eq!(IpAddr::deserialize(s), Ok(ip));
+            assert_eq!(ip.serialize(), s);
+        }
+        assert_eq!(
+            SocketAddrV4::deserialize("10.0.0.1:80"),
+            Ok(SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 80))
+        );
+        assert!(SocketAddr::deserialize("[fe80::1%eth0]:443").is_err());
+        assert!(Ipv4Addr::deserialize("256.0.0.1").is_err());
+        assert!(char::deserialize("ab").is_err());
+        assert!(char::deserialize("").is_err());
+        assert_eq!(char::deserialize("é"), Ok('é'));
+    }
+
+    #[test]
+    fn test_iso_and_seconds_duration() {
+        use std::time::Duration;
+        use xmlserde::{IsoDuration, SecondsDuration};
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"timeout")]
+        struct Timeout {
+            #[xmlserde(name = b"secs", ty = "attr")]
+            secs: SecondsDuration,
+            #[xmlserde(name = b"iso", ty = "attr")]
+            iso: IsoDuration,
+        }
+
+        let xml = r#"<timeout secs="30.5" iso="P1DT2H3M4.567S"/>"#;
+        let timeout = xml_deserialize_from_str::<Timeout>(xml).unwrap();
+        assert_eq!(timeout.secs, Duration::from_millis(30_500).into());
+        assert_eq!(timeout.iso, IsoDuration(Duration::from_millis(93_784_567)));
+        assert_eq!(xml_serialize(timeout), xml);
+
+        assert_eq!(IsoDuration(Duration::ZERO).serialize(), "PT0S");
+        assert_eq!(SecondsDuration::from(Duration::ZERO).serialize(), "0");
+        assert_eq!(
+            IsoDuration::deserialize("PT0S"),
+            Ok(IsoDuration(Duration::ZERO))
+        );
+        assert_eq!(
+            IsoDuration::deserialize("P0D"),
+            Ok(IsoDuration(Duration::ZERO))
+        );
+
+        let tiny = Duration::from_nanos(1_000_123);
+        assert_eq!(SecondsDuration::from(tiny).serialize(), "0.001000123");
+        assert_eq!(IsoDuration::from(tiny).serialize(), "PT0.001000123S");
+        assert_eq!(SecondsDuration::deserialize("0.001000123").unwrap().0, tiny);
+        assert_eq!(IsoDuration::deserialize("PT0.001000123S").unwrap().0, tiny);
+
+        let long = Duration::from_secs(3 * 86400 + 5);
+        assert_eq!(IsoDuration::from(long).serialize(), "P3DT5S");
+        assert_eq!(
+            IsoDuration::from(Duration::from_secs(2 * 86400)).serialize(),
+            "P2D"
+        );
+        assert_eq!(SecondsDuration::from(long).serialize(), "259205");
+
+        let day = 86400;
+        for (s, secs) in [
+            ("P1Y", 365 * day),
+            ("P2M", 60 * day),
+            ("P1W", 7 * day),
+            (
+                "P1Y2M3W4DT5H6M7S",
+                (365 + 60 + 21 + 4) * day + 5 * 3600 + 6 * 60 + 7,
+            ),
+            ("PT1.5M", 90),
+            ("PT36H", 36 * 3600),
+        ] {
+            assert_eq!(
+                IsoDuration::deserialize(s).unwrap().0,
+                Duration::from_secs(secs),
+                "{}",
+                s
+            );
+        }
+        assert_eq!(
+            IsoDuration::deserialize("PT0,5S").unwrap().0,
+            Duration::from_millis(500)
+        );
+        for s in [
+            "", "P", "PT", "1D", "P1H", "PT1D", "P1DT", "PT1S2M", "PT-1S", "PT1.S", "PTS",
+        ] {
+            assert!(IsoDuration::deserialize(s).is_err(), "{}", s);
+        }
+        assert_eq!(
+            SecondsDuration::deserialize("1e3").unwrap().0,
+            Duration::from_secs(1000)
+        );
+
+        // The durations out of range are errors instead of overflowing
+        assert!(IsoDuration::deserialize("PT340282366920938463463374607431.9S").is_err());
+        assert!(IsoDuration::deserialize("P300000000000000000000000000000YT1S").is_err());
+        assert!(IsoDuration::deserialize("P10000000000000000000000000000000DT1S").is_err());
+        assert!(
+            IsoDuration::deserialize("P10000000000000000000000Y100000000000000000000000M").is_err()
+        );
+        assert!(SecondsDuration::deserialize("340282366920938463463374607431.9").is_err());
+    }
+
+    #[test]
+    fn test_xml_value_from_str() {
+        use xmlserde::XmlValueFromStr;
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Version(u32, u32);
+
+        impl std::fmt::Display for Version {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}.{}", self.0, self.1)
+            }
+        }
+
+        impl std::str::FromStr for Version {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let (major, minor) = s.split_once('.').ok_or("expected major.minor")?;
+                let parse = |p: &str| p.parse::<u32>().map_err(|e| e.to_string());
+                Ok(Version(parse(major)?, parse(minor)?))
+            }
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"package")]
+        struct Package {
+            #[xmlserde(name = b"min", ty = "attr")]
+            min: XmlValueFromStr<Version>,
+            #[xmlserde(name = b"max", ty = "attr", xml_value = "display_fromstr")]
+            max: Option<Version>,
+            #[xmlserde(ty = "text", xml_value = "display_fromstr")]
+            current: Version,
+        }
+
+        let xml = r#"<package min="1.0" max="2.5">1.4</package>"#;
+        let package = xml_deserialize_from_str::<Package>(xml).unwrap();
+        assert_eq!(package.min.0, Version(1, 0));
+        assert_eq!(package.max, Some(Version(2, 5)));
+        assert_eq!(package.current, Version(1, 4));
+        assert_eq!(xml_serialize(package), xml);
+
+        let err = xml_deserialize_from_str::<Package>(r#"<package min="1">1.4</package>"#);
+        assert!(matches!(
+            err.unwrap_err().inner,
+            XmlError::DeserializeValueFailed { field: "min", .. }
+        ));
+        assert_eq!(
+            XmlValueFromStr::<Version>::deserialize("x").unwrap_err(),
+            "expected major.minor"
+        );
+    }
+
+    #[test]
+    fn test_rc_and_arc_fields() {
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        struct Style {
+            #[xmlserde(name = b"name", ty = "attr")]
+            name: Arc<String>,
+            #[xmlserde(ty = "text")]
+            css: Arc<String>,
+        }
+
+        #[derive(Debug, PartialEq, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"sheet")]
+        struct Sheet {
+            #[xmlserde(name = b"size", ty = "attr")]
+            size: Rc<u32>,
+            #[xmlserde(name = b"default", ty = "child")]
+            default: Arc<Style>,
+            #[xmlserde(name = b"fallback", ty = "child")]
+            fallback: Option<Rc<Style>>,
+            #[xmlserde(name = b"style", ty = "child")]
+            styles: Vec<Rc<Style>>,
+        }
+
+        let xml =
+            r#"<sheet size="2"><default name="d">a{}</default><style name="s">b{}</style></sheet>"#;
+        let sheet = xml_deserialize_from_str::<Sheet>(xml).unwrap();
+        assert_eq!(*sheet.size, 2);
+        assert_eq!(sheet.default.name.as_str(), "d");
+        assert_eq!(sheet.default.css.as_str(), "a{}");
+        assert!(sheet.fallback.is_none());
+        assert_eq!(sheet.styles[0].css.as_str(), "b{}");
+
+        // The shared values are written once for each reference
+        let shared = Rc::new(Style {
+            name: Arc::new("x".to_string()),
+            css: Arc::new("c".to_string()),
+        });
+        let sheet = Sheet {
+            size: Rc::new(1),
+            default: Arc::new(Style {
+                name: Arc::new("d".to_string()),
+                css: Arc::new("a".to_string()),
+            }),
+            fallback: Some(shared.clone()),
+            styles: vec![shared.clone(), shared],
+        };
+        assert_eq!(
+            xml_serialize(sheet),
+            r#"<sheet size="1"><default name="d">a</default><fallback name="x">c</fallback><style name="x">c</style><style name="x">c</style></sheet>"#
+        );
+    }
+
+    #[test]
+    fn test_key_attr() {
+        use std::collections::{BTreeMap, HashMap};
+        use xmlserde::XmlError;
+
+        #[derive(Debug, PartialEq, Clone, XmlDeserialize, XmlSerialize)]
+        struct User {
+            #[xmlserde(name = b"id", ty = "attr")]
+            id: u32,
+            #[xmlserde(ty = "text")]
+            name: String,
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"users")]
+        struct Users {
+            #[xmlserde(name = b"user", ty = "child", key_attr = b"id")]
+            by_id: BTreeMap<u32, User>,
+        }
+
+        #[derive(Debug, XmlDeserialize, XmlSerialize)]
+        #[xmlserde(root = b"users")]
+        struct UserIndex {
+            #[xmlserde(name = b"user", ty = "child", key_attr = b"id")]
+            by_id: HashMap<u32, User>,
+        }
+
+        let xml = r#"<users><user id="2">bob</user><user id="1">alice</user><user id="2">carol</user></users>"#;
+        let users = xml_deserialize_from_str::<Users>(xml).unwrap();
+        assert_eq!(users.by_id.len(), 2);
+        // The later child with the same key wins
+        assert_eq!(users.by_id[&2].name, "carol");
+        assert_eq!(
+            xml_serialize(users),
+            r#"<users><user id="1">alice</user><user id="2">carol</user></users>"#
+        );
+
+        let index = xml_deserialize_from_str::<UserIndex>(xml).unwrap();
+        assert_eq!(index.by_id[&1].name, "alice");
+        assert_eq!(index.by_id[&2].name, "carol");
+        let empty = UserIndex {
+            by_id: HashMap::new(),
+        };
+        assert_eq!(xml_serialize(empty), "<users/>");
+
+        let err = xml_deserialize_from_str::<Users>(r#"<users><user>dave</user></users>"#);
+        assert!(matches!(
+            err.unwrap_err().inner,
+            XmlError::MissingRequiredField {
+                element: "user",
+                field: "id",
+            }
+        ));
+        let err =
+            xml_deserialize_from_str::<UserIndex>(r#"<users><user id="x">eve</user></users>"#);
+        assert!(matches!(
+            err.unwrap_err().inner,
+            XmlError::DeserializeValueFailed { field: "id", .. }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_into_failure_keeps_value() {
+        use std::collections::HashMap;
+        use xmlserde::xml_deserialize_into;
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        struct N {
+            #[xmlserde(name = b"v", ty = "attr")]
+            v: u32,
+        }
+
+        #[derive(XmlDeserialize, Debug, PartialEq)]
+        #[xmlserde(root = b"config")]
+        struct Config {
+            #[xmlserde(name = b"name", ty = "attr", required)]
+            name: Option<String>,
+            #[xmlserde(name = b"port", ty = "attr", default = "default_port")]
+            port: u16,
+            #[xmlserde(name = b"n", ty = "child")]
+            n: Option<N>,
+            #[xmlserde(name = b"m", ty = "child")]
+            m: Result<N, ()>,
+            #[xmlserde(ty = "attr_map")]
+            extra: HashMap<String, String>,
+        }
+
+        fn default_port() -> u16 {
+            1
+        }
+
+        let xml = r#"<config name="app" port="80" x="1"><n v="3"/><m v="4"/></config>"#;
+        let mut config = xml_deserialize_from_str::<Config>(xml).unwrap();
+        let expected = xml_deserialize_from_str::<Config>(xml).unwrap();
+
+        // A failed patch leaves the value as it was
+        assert!(xml_deserialize_into(&mut config, r#"<config port="x"/>"#).is_err());
+        assert_eq!(config, expected);
+        let patch = r#"<config name="b" y="2"><n v="5"/><n v="x"/></config>"#;
+        assert!(xml_deserialize_into(&mut config, patch).is_err());
+        assert_eq!(config, expected);
+
+        // The fields with a default or of `Result<T, E>` are kept unless they appear
+        xml_deserialize_into(&mut config, r#"<config y="2"><n v="5"/></config>"#).unwrap();
+        assert_eq!(config.name.as_deref(), Some("app"));
+        assert_eq!(config.port, 80);
+        assert_eq!(config.n, Some(N { v: 5 }));
+        assert_eq!(config.m, Ok(N { v: 4 }));
+        assert_eq!(config.extra.len(), 2);
+        xml_deserialize_into(&mut config, r#"<config port="8"><m v="x"/></config>"#).unwrap();
+        assert_eq!(config.port, 8);
+        assert_eq!(config.m, Err(()));
+    }
 }